@@ -1,6 +1,6 @@
 use std::{
     collections::{HashMap, HashSet},
-    io,
+    env, fs, io,
     ops::Add,
 };
 
@@ -9,34 +9,135 @@ use itertools::Itertools;
 
 #[allow(dead_code)]
 fn part_1() -> Result<()> {
-    let (start, graph) = read_input()?;
+    let (start, _, graph) = read_input()?;
     let n = graph.num_reachable_nodes(start);
     dbg!(n / 2);
     Ok(())
 }
 
+/// Usage: `day_10 [--engine=flood|shoelace|both] [--render | --render=PATH]`
+///
+/// `--render` draws the maze to the terminal, using ANSI colors; `--render=PATH`
+/// writes it to a plain text file instead.
 fn main() -> Result<()> {
-    let (start, graph) = read_input()?;
+    let mut engine = Engine::Both;
+    let mut render = None;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--engine=flood" => engine = Engine::Flood,
+            "--engine=shoelace" => engine = Engine::Shoelace,
+            "--engine=both" => engine = Engine::Both,
+            "--render" => render = Some(None),
+            _ => match arg.strip_prefix("--render=") {
+                Some(path) => render = Some(Some(path.to_string())),
+                None => bail!("unknown argument: {arg:?}"),
+            },
+        }
+    }
+
+    let (start, start_tile, graph) = read_input()?;
+    dbg!(start_tile);
+
+    match render {
+        None => (),
+        Some(None) => print!("{}", graph.render(start, Style::Ansi)),
+        Some(Some(path)) => fs::write(&path, graph.render(start, Style::Plain))
+            .with_context(|| format!("failed to write {path:?}"))?,
+    }
 
-    let ph1 = graph.phase_1(start);
-    let ans = graph.phase_2(ph1);
+    let ans = match engine {
+        Engine::Flood => graph.enclosure_by_flood_fill(start)?,
+        Engine::Shoelace => graph.enclosure_by_shoelace(start),
+        Engine::Both => {
+            let flood = graph.enclosure_by_flood_fill(start)?;
+            let shoelace = graph.enclosure_by_shoelace(start);
+            ensure!(
+                flood == shoelace,
+                "engines disagree: flood fill {flood:?}, shoelace {shoelace:?}"
+            );
+            flood
+        }
+    };
     dbg!(ans);
 
     Ok(())
 }
 
-fn read_input() -> Result<(Point, Graph)> {
-    let mut lines: Vec<_> = io::stdin().lines().try_collect()?;
+/// How to count the tiles enclosed by the loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Engine {
+    /// Paint each side of the loop, and bucket-fill the rest.
+    Flood,
+    /// Shoelace formula, plus Pick's theorem.
+    Shoelace,
+    /// Run both, and check that they agree.
+    Both,
+}
+
+/// The tiles that aren't part of the main loop, split by which side of the
+/// loop they're on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Enclosure {
+    inside: usize,
+    outside: usize,
+}
+
+/// The start, the pipe under the S, and the graph of pipes.
+fn read_input() -> Result<(Point, char, Graph)> {
+    parse_input(io::stdin().lines().try_collect()?)
+}
 
+fn parse_input(mut lines: Vec<String>) -> Result<(Point, char, Graph)> {
     let start = find_start(&lines).context("no S")?;
 
-    // In my specific input, the start location is on a "|".
+    let start_tile = infer_start_tile(&lines, start)?;
     let col = start.col as usize;
-    lines[start.row as usize].replace_range(col..col + 1, "|");
+    lines[start.row as usize].replace_range(col..col + 1, &start_tile.to_string());
 
     let graph = parse_graph(&lines)?;
 
-    Ok((start, graph))
+    Ok((start, start_tile, graph))
+}
+
+/// Figure out which pipe is under the S, based on which of its neighbors
+/// connect back to it.
+fn infer_start_tile(lines: &[String], start: Point) -> Result<char> {
+    let tile_at = |p: Point| {
+        let row = lines.get(usize::try_from(p.row).ok()?)?;
+        row.chars().nth(usize::try_from(p.col).ok()?)
+    };
+    let connects_back = |d: Point| {
+        let Some(nbr_dirs) = tile_at(start + d).and_then(pipe_dirs) else {
+            return false;
+        };
+        nbr_dirs.contains(&d.invert())
+    };
+
+    let candidates: Vec<_> = PIPES
+        .into_iter()
+        .filter(|&c| pipe_dirs(c).unwrap().into_iter().all(connects_back))
+        .collect();
+    match candidates[..] {
+        [c] => Ok(c),
+        [] => bail!("no pipe shape fits the start tile"),
+        _ => bail!("ambiguous start tile; could be any of {candidates:?}"),
+    }
+}
+
+const PIPES: [char; 6] = ['|', '-', 'L', 'J', '7', 'F'];
+
+/// The two directions a pipe connects, or None if it's not a pipe.
+fn pipe_dirs(c: char) -> Option<[Point; 2]> {
+    let dirs = match c {
+        '|' => [UP, DOWN],
+        '-' => [LEFT, RIGHT],
+        'L' => [UP, RIGHT],
+        'J' => [UP, LEFT],
+        '7' => [LEFT, DOWN],
+        'F' => [DOWN, RIGHT],
+        _ => return None,
+    };
+    Some(dirs)
 }
 
 fn find_start(lines: &[String]) -> Option<Point> {
@@ -65,15 +166,10 @@ fn parse_graph(lines: &[String]) -> Result<Graph> {
             let p = (row, col).into();
             g.get_mut(p).tile = c;
 
-            let dirs = match c {
-                '|' => [UP, DOWN],
-                '-' => [LEFT, RIGHT],
-                'L' => [UP, RIGHT],
-                'J' => [UP, LEFT],
-                '7' => [LEFT, DOWN],
-                'F' => [DOWN, RIGHT],
-                '.' => continue,
-                _ => bail!("unexpected tile: {c:?}"),
+            let dirs = match pipe_dirs(c) {
+                Some(dirs) => dirs,
+                None if c == '.' => continue,
+                None => bail!("unexpected tile: {c:?}"),
             };
 
             for d in dirs {
@@ -132,20 +228,23 @@ impl Graph {
     /// Now the perimeter of every enclosed area has the correct color.
     fn phase_1(&self, start: Point) -> Phase1 {
         let mut out = Phase1::default();
-        self.dfs(start, &mut out);
+        // We leave the start through its first neighbor, so we'll eventually
+        // come back around to it through its second neighbor.
+        let came_from = self.get(start).neighbors[1];
+        self.dfs(start, came_from, &mut out);
         for p in &out.seen {
             out.colors.remove(p);
         }
         out
     }
 
-    fn dfs(&self, curr: Point, out: &mut Phase1) {
+    fn dfs(&self, curr: Point, came_from: Point, out: &mut Phase1) {
         if out.seen.contains(&curr) {
             return;
         }
         out.seen.insert(curr);
 
-        for (color, offset) in self.paint_offsets(curr, &out.seen) {
+        for (color, offset) in self.paint_offsets(curr, came_from) {
             let p = curr + offset;
             if self.in_bounds(p) {
                 out.colors.insert(p, color);
@@ -153,12 +252,12 @@ impl Graph {
         }
 
         for &nbr in &self.get(curr).neighbors {
-            self.dfs(nbr, out);
+            self.dfs(nbr, curr, out);
         }
     }
 
     #[must_use]
-    fn paint_offsets(&self, p: Point, seen: &HashSet<Point>) -> [(Color, Point); 2] {
+    fn paint_offsets(&self, p: Point, came_from: Point) -> [(Color, Point); 2] {
         // Arbitrary choice of orientation, to be the "default" one.
         let arrow_head = match self.get(p).tile {
             '|' => UP,
@@ -169,7 +268,7 @@ impl Graph {
             'F' => RIGHT,
             _ => panic!("not a pipe"),
         };
-        let inverted = seen.contains(&(p + arrow_head));
+        let inverted = came_from == p + arrow_head;
 
         // Assuming the default orientation, what would the output be?
         use Color::{Left as L, Right as R};
@@ -197,8 +296,8 @@ impl Graph {
     /// Perform an "MS Paint bucket-fill" on every blank region, based on the
     /// color of its perimeter.
     ///
-    /// Return the total number of "blue" and "red" tiles.
-    fn phase_2(&self, mut state: Phase1) -> HashMap<Color, usize> {
+    /// Return the color of every tile that's not on the main loop.
+    fn phase_2(&self, mut state: Phase1) -> HashMap<Point, Color> {
         for row in 0..self.dims().row {
             for col in 0..self.dims().col {
                 let p = Point { row, col };
@@ -212,11 +311,158 @@ impl Graph {
             }
         }
 
-        let mut color_freqs = HashMap::new();
-        for &col in state.colors.values() {
-            *color_freqs.entry(col).or_default() += 1;
+        state.colors
+    }
+
+    /// Which color is outside the loop? That's the color of any tile on the
+    /// edge of the grid.
+    ///
+    /// If the loop covers the whole edge, then every tile is inside it, and
+    /// this returns None.
+    fn outside_color(&self, colors: &HashMap<Point, Color>) -> Option<Color> {
+        let dims = self.dims();
+        let edge = (0..dims.row)
+            .flat_map(|row| [(row, 0), (row, dims.col - 1)])
+            .chain((0..dims.col).flat_map(|col| [(0, col), (dims.row - 1, col)]));
+        edge.map(|(row, col)| Point { row, col })
+            .find_map(|p| colors.get(&p).copied())
+    }
+
+    fn enclosure_by_flood_fill(&self, start: Point) -> Result<Enclosure> {
+        let colors = self.phase_2(self.phase_1(start));
+        let outside_color = self.outside_color(&colors);
+
+        let mut out = Enclosure {
+            inside: 0,
+            outside: 0,
+        };
+        for &color in colors.values() {
+            if Some(color) == outside_color {
+                out.outside += 1;
+            } else {
+                out.inside += 1;
+            }
+        }
+
+        let num_colors = colors.values().unique().count();
+        ensure!(
+            num_colors <= 1 || outside_color.is_some(),
+            "can't tell which color is outside"
+        );
+        Ok(out)
+    }
+}
+
+/// How to draw the maze.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Style {
+    /// For a terminal. The main loop is bold, junk pipes are dimmed, and the
+    /// inside of the loop is shaded.
+    Ansi,
+    /// For a text file. Junk pipes are drawn as `·` outside the loop, and
+    /// `░` inside it; the rest of the inside is shaded more heavily, with `▒`.
+    Plain,
+}
+
+impl Graph {
+    /// Redraw the maze with box-drawing characters.
+    fn render(&self, start: Point, style: Style) -> String {
+        const BOLD_YELLOW: &str = "\x1b[1;33m";
+        const BOLD_RED: &str = "\x1b[1;31m";
+        const DIM: &str = "\x1b[2m";
+        const INSIDE_BG: &str = "\x1b[42m";
+        const RESET: &str = "\x1b[0m";
+
+        let phase_1 = self.phase_1(start);
+        let main_loop = phase_1.seen.clone();
+        let colors = self.phase_2(phase_1);
+        let outside_color = self.outside_color(&colors);
+        let is_inside = |p: &Point| colors.get(p).is_some_and(|&c| Some(c) != outside_color);
+
+        let mut out = String::new();
+        for (row, l) in self.nodes.iter().enumerate() {
+            for (col, node) in l.iter().enumerate() {
+                let p = (row, col).into();
+                let glyph = box_drawing(node.tile);
+                match style {
+                    Style::Plain if main_loop.contains(&p) => out.push(glyph),
+                    Style::Plain => out.push(match (node.tile != '.', is_inside(&p)) {
+                        (true, false) => '·',
+                        (true, true) => '░',
+                        (false, false) => ' ',
+                        (false, true) => '▒',
+                    }),
+                    Style::Ansi => {
+                        if is_inside(&p) {
+                            out += INSIDE_BG;
+                        }
+                        if p == start {
+                            out += BOLD_RED;
+                        } else if main_loop.contains(&p) {
+                            out += BOLD_YELLOW;
+                        } else {
+                            out += DIM;
+                        }
+                        out.push(glyph);
+                        out += RESET;
+                    }
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+fn box_drawing(tile: char) -> char {
+    match tile {
+        '|' => '│',
+        '-' => '─',
+        'L' => '└',
+        'J' => '┘',
+        '7' => '┐',
+        'F' => '┌',
+        _ => ' ',
+    }
+}
+
+// Part 2 code; alternative engine.
+impl Graph {
+    /// The tiles of the main loop, in the order you'd walk them.
+    fn main_loop(&self, start: Point) -> Vec<Point> {
+        let mut path = vec![start];
+        let mut prev = start;
+        let mut curr = self.get(start).neighbors[0];
+        while curr != start {
+            path.push(curr);
+            let next = self.get(curr).neighbors.iter().find(|&&n| n != prev);
+            (prev, curr) = (curr, *next.expect("dead end"));
+        }
+        path
+    }
+
+    /// Think of the main loop as a polygon, whose vertices are the centers of
+    /// the loop's tiles. The shoelace formula gives its area, and then Pick's
+    /// theorem tells us how many lattice points (i.e. tiles) are strictly
+    /// inside it: `area = inside + boundary/2 - 1`.
+    fn enclosure_by_shoelace(&self, start: Point) -> Enclosure {
+        let path = self.main_loop(start);
+
+        let mut twice_area: isize = 0;
+        for (a, b) in path.iter().circular_tuple_windows() {
+            twice_area += a.col * b.row - b.col * a.row;
+        }
+        let twice_area = twice_area.unsigned_abs();
+
+        let boundary = path.len();
+        let inside = (twice_area + 2 - boundary) / 2;
+
+        let dims = self.dims();
+        let total = (dims.row * dims.col) as usize;
+        Enclosure {
+            inside,
+            outside: total - boundary - inside,
         }
-        color_freqs
     }
 
     fn explore_region(&self, p: Point, colors: &HashMap<Point, Color>) -> (HashSet<Point>, Color) {
@@ -334,6 +580,15 @@ impl Add for Point {
     }
 }
 
+impl Point {
+    fn invert(self) -> Self {
+        Self {
+            row: -self.row,
+            col: -self.col,
+        }
+    }
+}
+
 impl From<(usize, usize)> for Point {
     fn from((row, col): (usize, usize)) -> Self {
         Self {
@@ -342,3 +597,24 @@ impl From<(usize, usize)> for Point {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_render_shows_junk() -> Result<()> {
+        let maze = ["7.....", ".S--7.", ".|-.|.", ".L--J.", ".....|"];
+        let (start, start_tile, graph) = parse_input(maze.iter().map(|l| l.to_string()).collect())?;
+        assert_eq!(start_tile, 'F');
+        let expected = "\
+·     
+ ┌──┐ 
+ │░▒│ 
+ └──┘ 
+     ·
+";
+        assert_eq!(graph.render(start, Style::Plain), expected);
+        Ok(())
+    }
+}
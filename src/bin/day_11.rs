@@ -1,58 +1,305 @@
-use std::{
-    cmp::{max, min},
-    io,
-};
+use std::{cmp::min, env, io};
 
-use anyhow::Result;
-use itertools::Itertools;
+use anyhow::{bail, ensure, Context, Result};
 
+/// Usage: `day_11 [COMMAND] [FACTOR] [--buckets=N]`
+///
+/// Each empty row or column is replaced by FACTOR of them. Part 1 is 2, and
+/// part 2 (the default) is 1000000.
+///
+/// Commands:
+/// * (none): the sum of all pairwise distances.
+/// * `coords`: the expanded coordinates of every galaxy.
+/// * `dist I J`: the distance between galaxies I and J, numbered from 1 in
+///   reading order.
+/// * `neighbours`: each galaxy's nearest and farthest neighbour.
+/// * `histogram`: pairwise distances, grouped into N buckets (default 10).
 fn main() -> Result<()> {
-    let lines: Vec<_> = io::stdin().lines().try_collect()?;
-
-    let mut stars = vec![];
-    let mut empty_rows = vec![true; 140];
-    let mut empty_cols = vec![true; 140];
-    for (row, l) in lines.iter().enumerate() {
-        for (col, c) in l.chars().enumerate() {
-            if c == '#' {
-                stars.push(Point::from((row, col)));
-                empty_rows[row] = false;
-                empty_cols[col] = false;
-            }
+    let mut args: Vec<_> = env::args().skip(1).collect();
+    let command = match args.first().map(String::as_str) {
+        Some("coords" | "dist" | "neighbours" | "histogram") => args.remove(0),
+        _ => String::new(),
+    };
+    let mut pair = None;
+    if command == "dist" {
+        ensure!(args.len() >= 2, "usage: day_11 dist I J [FACTOR]");
+        let i: usize = args.remove(0).parse()?;
+        let j: usize = args.remove(0).parse()?;
+        pair = Some((i, j));
+    }
+
+    let mut factor = 10u128.pow(6);
+    let mut buckets = 10;
+    for arg in args {
+        if let Some(n) = arg.strip_prefix("--buckets=") {
+            buckets = n.parse()?;
+            ensure!(buckets != 0, "need at least one bucket");
+        } else if let Ok(n) = arg.parse() {
+            factor = n;
+        } else {
+            bail!("unknown argument: {arg:?}");
         }
     }
-    let n = stars.len();
+    ensure!(factor != 0, "expansion factor must be positive");
+
+    let universe = Universe::parse(&io::read_to_string(io::stdin())?)?;
+    let n = universe.len();
     dbg!(n);
-    dbg!(n_choose_2(n)); // ~100K = 10^5
-
-    let mut total = 0;
-    for i in 0..n {
-        for j in i + 1..n {
-            total += stars[i].manhattan_dist(stars[j]);
-
-            // Account for expansion.
-            let (min, max) = bounding_box(stars[i], stars[j]);
-            for row in min.row..max.row {
-                if empty_rows[row as usize] {
-                    // total += 1;
-                    total += 10usize.pow(6) - 1;
+
+    match command.as_str() {
+        "coords" => {
+            for i in 0..n {
+                let (row, col) = universe.expanded(i, factor)?;
+                println!("{}: ({row}, {col})", i + 1);
+            }
+        }
+        "dist" => {
+            let (i, j) = pair.unwrap();
+            let dist = universe.distance(universe.galaxy(i)?, universe.galaxy(j)?, factor)?;
+            dbg!(dist);
+        }
+        "neighbours" => {
+            for i in 0..n {
+                let Some((near, far)) = universe.neighbours(i, factor)? else {
+                    println!("{}: no neighbours", i + 1);
+                    continue;
+                };
+                let near_dist = universe.distance(i, near, factor)?;
+                let far_dist = universe.distance(i, far, factor)?;
+                println!(
+                    "{}: nearest {} ({near_dist}), farthest {} ({far_dist})",
+                    i + 1,
+                    near + 1,
+                    far + 1,
+                );
+            }
+        }
+        "histogram" => {
+            for (lo, hi, count) in universe.histogram(factor, buckets)? {
+                println!("{lo}..={hi}: {count}");
+            }
+        }
+        _ => {
+            let total = universe.sum_of_distances(factor)?;
+            dbg!(total);
+        }
+    }
+
+    Ok(())
+}
+
+/// The galaxies, before expansion.
+///
+/// Expansion isn't applied up front; instead, we remember how many empty
+/// lines come before each row and column, and work out expanded coordinates
+/// on demand, for whatever factor is asked for.
+#[derive(Debug, Clone)]
+struct Universe {
+    /// In reading order.
+    stars: Vec<Point>,
+    #[cfg(test)]
+    empty_rows: Vec<bool>,
+    #[cfg(test)]
+    empty_cols: Vec<bool>,
+    /// The number of empty rows strictly before each row.
+    empty_rows_before: Vec<u128>,
+    empty_cols_before: Vec<u128>,
+}
+
+impl Universe {
+    fn parse(s: &str) -> Result<Self> {
+        let lines: Vec<_> = s.lines().collect();
+        let nrows = lines.len();
+        let ncols = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+
+        let mut stars = vec![];
+        let mut empty_rows = vec![true; nrows];
+        let mut empty_cols = vec![true; ncols];
+        for (row, l) in lines.iter().enumerate() {
+            for (col, c) in l.chars().enumerate() {
+                match c {
+                    '.' => (),
+                    '#' => {
+                        stars.push(Point::from((row, col)));
+                        empty_rows[row] = false;
+                        empty_cols[col] = false;
+                    }
+                    _ => bail!("unexpected char {c:?} at {}:{}", row + 1, col + 1),
                 }
             }
-            for col in min.col..max.col {
-                if empty_cols[col as usize] {
-                    // total += 1;
-                    total += 10usize.pow(6) - 1;
+        }
+
+        Ok(Self {
+            stars,
+            empty_rows_before: count_before(&empty_rows),
+            empty_cols_before: count_before(&empty_cols),
+            #[cfg(test)]
+            empty_rows,
+            #[cfg(test)]
+            empty_cols,
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.stars.len()
+    }
+
+    /// Look up a galaxy by its 1-based number.
+    fn galaxy(&self, name: usize) -> Result<usize> {
+        ensure!(
+            (1..=self.len()).contains(&name),
+            "no galaxy {name}; expected 1..={}",
+            self.len()
+        );
+        Ok(name - 1)
+    }
+
+    /// Where the `i`th galaxy ends up, once each empty line is replaced by
+    /// `factor` of them. Fails if that doesn't fit in a u128.
+    fn expanded(&self, i: usize, factor: u128) -> Result<(u128, u128)> {
+        let p = self.stars[i];
+        let (row, col) = (p.row as usize, p.col as usize);
+        let expand = |x: usize, empty_before: u128| {
+            (factor - 1)
+                .checked_mul(empty_before)
+                .and_then(|extra| extra.checked_add(x as u128))
+                .with_context(|| format!("galaxy {} overflows at factor {factor}", i + 1))
+        };
+        Ok((
+            expand(row, self.empty_rows_before[row])?,
+            expand(col, self.empty_cols_before[col])?,
+        ))
+    }
+
+    fn distance(&self, i: usize, j: usize, factor: u128) -> Result<u128> {
+        let (r1, c1) = self.expanded(i, factor)?;
+        let (r2, c2) = self.expanded(j, factor)?;
+        (r1.abs_diff(r2))
+            .checked_add(c1.abs_diff(c2))
+            .with_context(|| format!("distance overflows at factor {factor}"))
+    }
+
+    /// The nearest and farthest other galaxies from `i`. Ties go to the
+    /// lowest-numbered galaxy.
+    fn neighbours(&self, i: usize, factor: u128) -> Result<Option<(usize, usize)>> {
+        let others: Vec<_> = (0..self.len())
+            .filter(|&j| j != i)
+            .map(|j| Ok((j, self.distance(i, j, factor)?)))
+            .collect::<Result<_>>()?;
+        let near = others.iter().min_by_key(|&&(_, d)| d);
+        let far = others.iter().rev().max_by_key(|&&(_, d)| d);
+        Ok(near.zip(far).map(|(&(near, _), &(far, _))| (near, far)))
+    }
+
+    /// Pairwise distances, counted in `buckets` equal-width ranges spanning
+    /// the smallest to the largest distance. Returns `(lo, hi, count)`, with
+    /// inclusive bounds.
+    fn histogram(&self, factor: u128, buckets: usize) -> Result<Vec<(u128, u128, usize)>> {
+        let mut dists = vec![];
+        for i in 0..self.len() {
+            for j in i + 1..self.len() {
+                dists.push(self.distance(i, j, factor)?);
+            }
+        }
+        let (Some(&lo), Some(&hi)) = (dists.iter().min(), dists.iter().max()) else {
+            return Ok(vec![]);
+        };
+
+        let width = (hi - lo + 1).div_ceil(buckets as u128);
+        let mut counts = vec![0; buckets];
+        for d in dists {
+            counts[((d - lo) / width) as usize] += 1;
+        }
+
+        let mut out = vec![];
+        for (k, count) in counts.into_iter().enumerate() {
+            let start = lo + k as u128 * width;
+            if start > hi {
+                break;
+            }
+            out.push((start, min(start + width - 1, hi), count));
+        }
+        Ok(out)
+    }
+
+    /// The sum of the distances between every pair of galaxies.
+    ///
+    /// Manhattan distance splits into a row part and a column part, so we can
+    /// handle each axis separately, in O(n log n) time.
+    fn sum_of_distances(&self, factor: u128) -> Result<u128> {
+        let coords: Vec<_> = (0..self.len())
+            .map(|i| self.expanded(i, factor))
+            .collect::<Result<_>>()?;
+        let (rows, cols) = coords.into_iter().unzip();
+        sum_of_differences(rows)
+            .zip(sum_of_differences(cols))
+            .and_then(|(rows, cols)| rows.checked_add(cols))
+            .with_context(|| format!("sum of distances overflows at factor {factor}"))
+    }
+
+    /// The original O(n^2 * size) algorithm. Only useful for checking the fast
+    /// one.
+    #[cfg(test)]
+    fn sum_of_distances_quadratic(&self, factor: u128) -> u128 {
+        let stars = &self.stars;
+        let n = stars.len();
+        let mut total = 0;
+        for i in 0..n {
+            for j in i + 1..n {
+                total += stars[i].manhattan_dist(stars[j]) as u128;
+
+                // Account for expansion.
+                let (min, max) = bounding_box(stars[i], stars[j]);
+                for row in min.row..max.row {
+                    if self.empty_rows[row as usize] {
+                        total += factor - 1;
+                    }
+                }
+                for col in min.col..max.col {
+                    if self.empty_cols[col as usize] {
+                        total += factor - 1;
+                    }
                 }
             }
         }
+        total
     }
-    dbg!(total);
+}
 
-    Ok(())
+/// For each index, the number of `true`s strictly before it.
+fn count_before(flags: &[bool]) -> Vec<u128> {
+    flags
+        .iter()
+        .scan(0, |count, &f| {
+            let before = *count;
+            *count += u128::from(f);
+            Some(before)
+        })
+        .collect()
+}
+
+/// The sum of `|x_i - x_j|` over all pairs.
+///
+/// Once sorted, each `x_k` is the larger of the pair when paired with the `k`
+/// elements before it; so it contributes `k * x_k` minus the sum of those
+/// elements. Returns `None` on overflow.
+fn sum_of_differences(mut xs: Vec<u128>) -> Option<u128> {
+    xs.sort_unstable();
+    let mut total: u128 = 0;
+    let mut prefix_sum: u128 = 0;
+    for (k, &x) in xs.iter().enumerate() {
+        let term = (k as u128).checked_mul(x)? - prefix_sum;
+        total = total.checked_add(term)?;
+        prefix_sum = prefix_sum.checked_add(x)?;
+    }
+    Some(total)
 }
 
 /// Returns (top_left, bot_right).
+#[cfg(test)]
 fn bounding_box(p1: Point, p2: Point) -> (Point, Point) {
+    use std::cmp::max;
+
     let top_left = Point {
         row: min(p1.row, p2.row),
         col: min(p1.col, p2.col),
@@ -64,10 +311,6 @@ fn bounding_box(p1: Point, p2: Point) -> (Point, Point) {
     (top_left, bot_right)
 }
 
-fn n_choose_2(n: usize) -> usize {
-    n * (n - 1) / 2
-}
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Point {
     row: isize,
@@ -83,8 +326,59 @@ impl From<(usize, usize)> for Point {
     }
 }
 
+#[cfg(test)]
 impl Point {
     fn manhattan_dist(self, other: Self) -> usize {
         self.row.abs_diff(other.row) + self.col.abs_diff(other.col)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+...#......
+.......#..
+#.........
+..........
+......#...
+.#........
+.........#
+..........
+.......#..
+#...#.....
+";
+
+    #[test]
+    fn sample() -> Result<()> {
+        let universe = Universe::parse(SAMPLE)?;
+        assert_eq!(universe.sum_of_distances(2)?, 374);
+        assert_eq!(universe.sum_of_distances(10)?, 1030);
+        assert_eq!(universe.sum_of_distances(100)?, 8410);
+        assert_eq!(universe.distance(4, 8, 2)?, 9);
+        Ok(())
+    }
+
+    #[test]
+    fn agrees_with_quadratic() -> Result<()> {
+        let universe = Universe::parse(SAMPLE)?;
+        for factor in [1, 2, 10, 10u128.pow(6)] {
+            assert_eq!(
+                universe.sum_of_distances(factor)?,
+                universe.sum_of_distances_quadratic(factor),
+                "factor {factor}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn overflow_is_an_error() -> Result<()> {
+        let universe = Universe::parse(SAMPLE)?;
+        assert!(universe.expanded(8, u128::MAX).is_err());
+        assert!(universe.sum_of_distances(u128::MAX / 2).is_err());
+        assert_eq!(universe.expanded(2, u128::MAX)?, (2, 0));
+        Ok(())
+    }
+}
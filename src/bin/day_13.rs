@@ -1,40 +1,91 @@
-use std::io;
+use std::{cmp::min, env, io};
 
-use anyhow::{bail, ensure, Context, Result};
+use anyhow::{bail, ensure, Result};
 use itertools::Itertools;
 
-#[allow(dead_code)]
-fn part_1() -> Result<()> {
-    let mut ans = 0;
-    for mut input in read_input()? {
-        match input.find_reflection()? {
-            Reflection::Row(r) => ans += r * 100,
-            Reflection::Col(c) => ans += c,
+/// Usage: `day_13 [SMUDGES] [--show | --symmetries]`
+///
+/// Find the line of reflection in each grid that's off by exactly SMUDGES
+/// cells. Part 1 is 0, and part 2 (the default) is 1.
+///
+/// `--show` also prints each reflection, along with its smudges.
+///
+/// `--symmetries` instead lists every kind of symmetry each grid has, with at
+/// most SMUDGES smudges.
+fn main() -> Result<()> {
+    let mut num_smudges = 1;
+    let mut show = false;
+    let mut symmetries = false;
+    for arg in env::args().skip(1) {
+        if arg == "--show" {
+            show = true;
+        } else if arg == "--symmetries" {
+            symmetries = true;
+        } else if let Ok(k) = arg.parse() {
+            num_smudges = k;
+        } else {
+            bail!("unknown argument: {arg:?}");
         }
     }
-    dbg!(ans);
-    Ok(())
-}
 
-fn main() -> Result<()> {
+    if symmetries {
+        for (i, input) in read_input()?.into_iter().enumerate() {
+            println!("grid {}:", i + 1);
+            for (sym, score) in input.find_symmetries(num_smudges) {
+                println!("  {sym:?}: {score} smudges");
+            }
+        }
+        return Ok(());
+    }
+
     let mut ans = 0;
-    for mut input in read_input()? {
-        match input.find_new_reflection()? {
-            Reflection::Row(r) => ans += r * 100,
-            Reflection::Col(c) => ans += c,
+    for (i, input) in read_input()?.into_iter().enumerate() {
+        let found = input.find_smudged_reflection(num_smudges)?;
+        if show {
+            let smudges = found
+                .smudges
+                .iter()
+                .map(|s| format!("{:?}~{:?}", s.cell, s.mirror))
+                .join(", ");
+            println!(
+                "grid {}: {:?}; smudges: [{smudges}]",
+                i + 1,
+                found.reflection
+            );
         }
+        ans += summarize(found.reflection)?;
     }
     dbg!(ans);
     Ok(())
 }
 
+/// A row is worth 100 per row above it, and a column 1 per column to its left.
+fn summarize(reflection: Reflection) -> Result<usize> {
+    match reflection {
+        Reflection::Row(r) => Ok(r * 100),
+        Reflection::Col(c) => Ok(c),
+        other => bail!("not a line of reflection: {other:?}"),
+    }
+}
+
 fn read_input() -> Result<Vec<Input>> {
     let stdin = io::read_to_string(io::stdin())?;
     stdin.split("\n\n").map(parse_grid).collect()
 }
 
 fn parse_grid(s: &str) -> Result<Input> {
-    let grid = s.lines().map(parse_row).try_collect()?;
+    let grid: Vec<_> = s.lines().map(parse_row).try_collect()?;
+    ensure!(!grid.is_empty(), "empty grid");
+    ensure!(
+        grid.iter().map(Vec::len).all_equal(),
+        "rows have different lengths"
+    );
+    ensure!(
+        grid.len() <= 128 && grid[0].len() <= 128,
+        "grid too large to bit-pack: {}x{}",
+        grid.len(),
+        grid[0].len()
+    );
     Ok(Input { grid })
 }
 
@@ -56,74 +107,212 @@ struct Input {
 enum Reflection {
     Row(usize),
     Col(usize),
+    /// Rotation by 180 degrees, about the center of the grid.
+    HalfTurn,
+    /// Rotation by 90 degrees, of the square subgrid with its top-left corner
+    /// at (`row`, `col`).
+    QuarterTurn {
+        row: usize,
+        col: usize,
+        size: usize,
+    },
+    /// Across the line from the top-left to the bottom-right corner. Square
+    /// grids only.
+    Diagonal,
+    /// Across the line from the top-right to the bottom-left corner. Square
+    /// grids only.
+    AntiDiagonal,
+}
+
+/// A reflection that's only off by a few cells.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Smudged {
+    reflection: Reflection,
+    smudges: Vec<Smudge>,
+}
+
+/// A pair of cells that ought to mirror each other, but don't. Fixing either
+/// one would do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Smudge {
+    /// (row, col), on the top or left side of the line.
+    cell: (usize, usize),
+    mirror: (usize, usize),
 }
 
 impl Input {
-    fn find_new_reflection(&mut self) -> Result<Reflection> {
-        let existing = self.find_reflection().context("unmodified")?;
+    /// Find the unique line of reflection that's off by exactly `num_smudges`
+    /// cells.
+    fn find_smudged_reflection(&self, num_smudges: usize) -> Result<Smudged> {
+        let mut found = self.find_smudged_reflections(num_smudges);
+        ensure!(
+            found.len() == 1,
+            "expected exactly one line of reflection with {num_smudges} smudges; found {}",
+            found.len()
+        );
+        Ok(found.pop().unwrap())
+    }
+
+    fn find_smudged_reflections(&self, num_smudges: usize) -> Vec<Smudged> {
+        let rows = pack(&self.grid);
+        let cols = pack(&self.transposed().grid);
 
+        let mut out = vec![];
+        for r in smudged_axes(&rows, num_smudges) {
+            out.push(Smudged {
+                reflection: Reflection::Row(r),
+                smudges: smudges(&rows, r),
+            });
+        }
+        for c in smudged_axes(&cols, num_smudges) {
+            // Swap back to (row, col).
+            let smudges = smudges(&cols, c)
+                .into_iter()
+                .map(|s| Smudge {
+                    cell: (s.cell.1, s.cell.0),
+                    mirror: (s.mirror.1, s.mirror.0),
+                })
+                .collect();
+            out.push(Smudged {
+                reflection: Reflection::Col(c),
+                smudges,
+            });
+        }
+        out
+    }
+
+    /// Every symmetry with at most `max_smudges` smudges, along with its
+    /// number of smudges.
+    ///
+    /// There are lots of tiny square subgrids with quarter-turn symmetry, so
+    /// we only report the largest ones.
+    fn find_symmetries(&self, max_smudges: usize) -> Vec<(Reflection, usize)> {
         let (nrows, ncols) = self.dims();
-        for i in 0..nrows {
-            for j in 0..ncols {
-                self.grid[i][j] ^= true;
-                for r in self.find_reflections() {
-                    if r != existing {
-                        return Ok(r);
+        let mut candidates: Vec<_> = (1..nrows).map(Reflection::Row).collect();
+        candidates.extend((1..ncols).map(Reflection::Col));
+        candidates.extend([
+            Reflection::HalfTurn,
+            Reflection::Diagonal,
+            Reflection::AntiDiagonal,
+        ]);
+        let mut out: Vec<_> = candidates
+            .into_iter()
+            .filter_map(|r| Some((r, self.score(r)?)))
+            .filter(|&(_, score)| score <= max_smudges)
+            .collect();
+
+        for size in (2..=min(nrows, ncols)).rev() {
+            let len = out.len();
+            for row in 0..=nrows - size {
+                for col in 0..=ncols - size {
+                    let r = Reflection::QuarterTurn { row, col, size };
+                    let score = self.score(r).unwrap();
+                    if score <= max_smudges {
+                        out.push((r, score));
                     }
                 }
-                self.grid[i][j] ^= true; // restore orig state
+            }
+            if out.len() > len {
+                break;
             }
         }
 
-        bail!("failed to find smudge")
+        out
     }
 
-    fn find_reflections(&mut self) -> Vec<Reflection> {
-        let rows = self.find_row_reflections();
-        let cols = self.find_col_reflections();
-
-        let rows = rows.into_iter().map(Reflection::Row);
-        let cols = cols.into_iter().map(Reflection::Col);
-        rows.chain(cols).collect()
-    }
+    /// The number of smudges: that is, how many cells would need fixing for
+    /// the grid to have this symmetry. Returns `None` if the grid is the wrong
+    /// shape for it.
+    fn score(&self, reflection: Reflection) -> Option<usize> {
+        let (nrows, ncols) = self.dims();
+        let square = nrows == ncols;
 
-    fn find_reflection(&mut self) -> Result<Reflection> {
-        let out = self.find_reflections();
-        ensure!(out.len() == 1, "expected exactly one line of reflection");
-        Ok(out[0])
+        // The reflections and the half turn pair up cells, so each smudge
+        // shows up as two differences.
+        let score = match reflection {
+            Reflection::Row(r) => {
+                if !(1..nrows).contains(&r) {
+                    return None;
+                }
+                num_mismatches(&pack(&self.grid), r, usize::MAX).unwrap()
+            }
+            Reflection::Col(c) => {
+                if !(1..ncols).contains(&c) {
+                    return None;
+                }
+                num_mismatches(&pack(&self.transposed().grid), c, usize::MAX).unwrap()
+            }
+            Reflection::HalfTurn => num_differences(&self.grid, &self.rotated_half().grid) / 2,
+            Reflection::Diagonal if square => {
+                num_differences(&self.grid, &self.transposed().grid) / 2
+            }
+            Reflection::AntiDiagonal if square => {
+                let image = self.transposed().rotated_half();
+                num_differences(&self.grid, &image.grid) / 2
+            }
+            Reflection::Diagonal | Reflection::AntiDiagonal => return None,
+            Reflection::QuarterTurn { row, col, size } => {
+                if size == 0 || row + size > nrows || col + size > ncols {
+                    return None;
+                }
+                self.subgrid(row, col, size).quarter_turn_score()
+            }
+        };
+        Some(score)
     }
 
-    fn find_row_reflections(&mut self) -> Vec<usize> {
-        let n = self.grid.len();
-        let mut out = vec![];
+    /// The cells come in orbits of four under a quarter turn (or one, at the
+    /// center). Each orbit needs its minority cells fixed.
+    fn quarter_turn_score(&self) -> usize {
+        let r1 = self.rotated_quarter();
+        let r2 = r1.rotated_quarter();
+        let r3 = r2.rotated_quarter();
 
-        for second_half in [false, true] {
-            for i in 1..=n / 2 {
-                if self.is_mirrored_at(i) {
-                    let row_idx = if second_half { n - i } else { i };
-                    out.push(row_idx);
-                }
+        let (n, _) = self.dims();
+        let mut total = 0;
+        for i in 0..n {
+            for j in 0..n {
+                let images = [&self.grid, &r1.grid, &r2.grid, &r3.grid];
+                let num_set = images.iter().filter(|g| g[i][j]).count();
+                total += min(num_set, 4 - num_set);
             }
-            self.grid.reverse(); // check the bottom half
         }
 
-        out
+        // Each orbit of four got counted once per cell.
+        total / 4
+    }
+
+    fn subgrid(&self, row: usize, col: usize, size: usize) -> Input {
+        let grid = self.grid[row..row + size]
+            .iter()
+            .map(|r| r[col..col + size].to_vec())
+            .collect();
+        Input { grid }
     }
 
-    /// Only works for row indices in the top half.
-    fn is_mirrored_at(&mut self, row_idx: usize) -> bool {
-        assert!(row_idx <= self.grid.len() / 2);
-        let i = row_idx;
-        self.grid[0..i].reverse();
-        let out = self.grid[0..i] == self.grid[i..2 * i];
-        self.grid[0..i].reverse(); // restore original state
+    fn transposed(&self) -> Input {
+        let mut out = Input {
+            grid: self.grid.clone(),
+        };
+        out.transpose();
         out
     }
 
-    fn find_col_reflections(&mut self) -> Vec<usize> {
-        self.transpose();
-        let out = self.find_row_reflections();
-        self.transpose();
+    fn rotated_half(&self) -> Input {
+        let mut grid = self.grid.clone();
+        grid.reverse();
+        for row in &mut grid {
+            row.reverse();
+        }
+        Input { grid }
+    }
+
+    /// Clockwise.
+    fn rotated_quarter(&self) -> Input {
+        let mut out = self.transposed();
+        for row in &mut out.grid {
+            row.reverse();
+        }
         out
     }
 
@@ -131,9 +320,9 @@ impl Input {
         let (nrows, ncols) = self.dims();
 
         let mut out = vec![vec![false; nrows]; ncols]; // note the swap !
-        for i in 0..nrows {
-            for j in 0..ncols {
-                out[j][i] = self.grid[i][j];
+        for (i, row) in self.grid.iter().enumerate() {
+            for (j, &cell) in row.iter().enumerate() {
+                out[j][i] = cell;
             }
         }
         self.grid = out;
@@ -145,3 +334,192 @@ impl Input {
         (nrows, ncols)
     }
 }
+
+/// Each row as a bitmask, with column `j` in bit `j`.
+fn pack(grid: &[Vec<bool>]) -> Vec<u128> {
+    grid.iter()
+        .map(|row| {
+            (row.iter().enumerate())
+                .filter(|(_, &b)| b)
+                .fold(0, |mask, (j, _)| mask | 1 << j)
+        })
+        .collect()
+}
+
+/// The number of cells that differ between two grids of the same shape.
+fn num_differences(a: &[Vec<bool>], b: &[Vec<bool>]) -> usize {
+    (pack(a).iter().zip(pack(b)))
+        .map(|(x, y)| (x ^ y).count_ones() as usize)
+        .sum()
+}
+
+/// The lines of reflection (given as the number of rows above them) that are
+/// off by exactly `num_smudges` cells.
+fn smudged_axes(rows: &[u128], num_smudges: usize) -> Vec<usize> {
+    (1..rows.len())
+        .filter(|&i| num_mismatches(rows, i, num_smudges) == Some(num_smudges))
+        .collect()
+}
+
+/// The number of cells that don't match their mirror image across the line
+/// with `i` rows above it. Gives up early, once there are more than `limit`.
+fn num_mismatches(rows: &[u128], i: usize, limit: usize) -> Option<usize> {
+    let mut count = 0;
+    for (above, below) in rows[..i].iter().rev().zip(&rows[i..]) {
+        count += (above ^ below).count_ones() as usize;
+        if count > limit {
+            return None;
+        }
+    }
+    Some(count)
+}
+
+/// The cells that don't match their mirror image across the line with `i`
+/// rows above it.
+fn smudges(rows: &[u128], i: usize) -> Vec<Smudge> {
+    let mut out = vec![];
+    for d in 0..i.min(rows.len() - i) {
+        let (above, below) = (i - 1 - d, i + d);
+        let mut diff = rows[above] ^ rows[below];
+        while diff != 0 {
+            let j = diff.trailing_zeros() as usize;
+            out.push(Smudge {
+                cell: (above, j),
+                mirror: (below, j),
+            });
+            diff &= diff - 1;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: [&str; 2] = [
+        "\
+#.##..##.
+..#.##.#.
+##......#
+##......#
+..#.##.#.
+..##..##.
+#.#.##.#.",
+        "\
+#...##..#
+#....#..#
+..##..###
+#####.##.
+#####.##.
+..##..###
+#....#..#",
+    ];
+
+    fn grid(s: &str) -> Input {
+        parse_grid(s).unwrap()
+    }
+
+    #[test]
+    fn sample() -> Result<()> {
+        for (num_smudges, expected) in [(0, 405), (1, 400)] {
+            let mut ans = 0;
+            for s in SAMPLE {
+                ans += summarize(grid(s).find_smudged_reflection(num_smudges)?.reflection)?;
+            }
+            assert_eq!(ans, expected, "{num_smudges} smudges");
+        }
+        Ok(())
+    }
+
+    /// The smudges are where the puzzle says they are.
+    #[test]
+    fn sample_smudges() -> Result<()> {
+        let found = grid(SAMPLE[0]).find_smudged_reflection(1)?;
+        assert_eq!(found.reflection, Reflection::Row(3));
+        let smudge = Smudge {
+            cell: (0, 0),
+            mirror: (5, 0),
+        };
+        assert_eq!(found.smudges, [smudge]);
+
+        let found = grid(SAMPLE[1]).find_smudged_reflection(1)?;
+        assert_eq!(found.reflection, Reflection::Row(1));
+        let smudge = Smudge {
+            cell: (0, 4),
+            mirror: (1, 4),
+        };
+        assert_eq!(found.smudges, [smudge]);
+
+        // Without smudges, the first reflects across a column.
+        let found = grid(SAMPLE[0]).find_smudged_reflection(0)?;
+        assert_eq!(found.reflection, Reflection::Col(5));
+        assert!(found.smudges.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn rows_and_cols() {
+        let g = grid("#.\n#.\n..");
+        assert_eq!(g.score(Reflection::Row(1)), Some(0));
+        assert_eq!(g.score(Reflection::Row(2)), Some(1));
+        assert_eq!(g.score(Reflection::Col(1)), Some(2));
+
+        // Lines along the edges don't count.
+        assert_eq!(g.score(Reflection::Row(0)), None);
+        assert_eq!(g.score(Reflection::Row(3)), None);
+        assert_eq!(g.score(Reflection::Col(0)), None);
+        assert_eq!(g.score(Reflection::Col(2)), None);
+    }
+
+    #[test]
+    fn half_turn() {
+        assert_eq!(grid("#..\n..#").score(Reflection::HalfTurn), Some(0));
+        assert_eq!(grid("#..\n...").score(Reflection::HalfTurn), Some(1));
+        // The center cell maps to itself.
+        assert_eq!(grid("...\n.#.\n...").score(Reflection::HalfTurn), Some(0));
+    }
+
+    #[test]
+    fn diagonals() {
+        let g = grid("#.\n..");
+        assert_eq!(g.score(Reflection::Diagonal), Some(0));
+        assert_eq!(g.score(Reflection::AntiDiagonal), Some(1));
+
+        let g = grid(".#\n..");
+        assert_eq!(g.score(Reflection::Diagonal), Some(1));
+        assert_eq!(g.score(Reflection::AntiDiagonal), Some(0));
+
+        let g = grid("##\n..");
+        assert_eq!(g.score(Reflection::Diagonal), Some(1));
+        assert_eq!(g.score(Reflection::AntiDiagonal), Some(1));
+
+        // Only square grids have diagonals.
+        let g = grid("#..\n...");
+        assert_eq!(g.score(Reflection::Diagonal), None);
+        assert_eq!(g.score(Reflection::AntiDiagonal), None);
+    }
+
+    #[test]
+    fn quarter_turns() {
+        let quarter = |row, col, size| Reflection::QuarterTurn { row, col, size };
+
+        let g = grid("#.#\n...\n#.#");
+        assert_eq!(g.score(quarter(0, 0, 3)), Some(0));
+        // One corner in four set, so it's cheaper to clear it.
+        assert_eq!(g.score(quarter(0, 0, 2)), Some(1));
+
+        // Two of the four edge midpoints set: either way takes two fixes.
+        let g = grid(".#.\n#..\n...");
+        assert_eq!(g.score(quarter(0, 0, 3)), Some(2));
+        assert_eq!(g.score(quarter(1, 1, 2)), Some(0));
+
+        // Subgrids have to fit, and be non-empty; the grid needn't be square.
+        let g = grid("#..\n...");
+        assert_eq!(g.score(quarter(0, 1, 2)), Some(0));
+        assert_eq!(g.score(quarter(0, 0, 0)), None);
+        assert_eq!(g.score(quarter(0, 0, 3)), None);
+        assert_eq!(g.score(quarter(1, 0, 2)), None);
+        assert_eq!(g.score(quarter(0, 2, 2)), None);
+    }
+}
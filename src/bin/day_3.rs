@@ -1,22 +1,19 @@
 use std::{
     collections::{HashMap, HashSet},
-    io,
+    env, io, iter,
     ops::Add,
-    result::Result as StdResult,
 };
 
-use anyhow::{ensure, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
 use itertools::Itertools;
-use once_cell::sync::Lazy;
-use regex::Regex;
 
 #[allow(dead_code)]
 fn part_1() -> Result<()> {
     let grid = read_grid()?;
-    let numbers = grid.find_numbers()?;
+    let numbers = grid.find_numbers(ParseOptions::from_args()?)?;
 
     let mut sum = 0;
-    for n in numbers {
+    for n in &numbers {
         if grid.is_part_number(n) {
             sum += n.value;
         }
@@ -27,32 +24,71 @@ fn part_1() -> Result<()> {
 }
 
 fn read_grid() -> Result<Grid> {
-    let mut lines: Vec<_> = io::stdin().lines().collect::<StdResult<_, _>>()?;
+    parse_grid(&io::read_to_string(io::stdin())?)
+}
+
+fn parse_grid(s: &str) -> Result<Grid> {
+    let lines: Vec<_> = s.lines().collect();
     ensure!(!lines.is_empty(), "empty");
-    ensure!(lines.iter().all(|l| l.len() == lines[0].len()), "jagged");
 
-    // Pad with '.'s, so we can skip bounds checks.
-    for l in &mut lines {
-        l.insert(0, '.');
-        l.push('.');
+    // Ragged lines are padded on the right with '.'s, so the grid is rectangular.
+    let rows: Vec<Vec<char>> = lines.iter().map(|l| l.chars().collect()).collect();
+    let line_lens = rows.iter().map(Vec::len).collect();
+    let width = rows.iter().map(Vec::len).max().unwrap();
+
+    // Also pad with '.'s around the edges, so we can skip bounds checks.
+    let mut padded = vec![vec!['.'; width + 2]];
+    for r in rows {
+        let mut row = vec!['.'];
+        let len = r.len();
+        row.extend(r);
+        row.extend(iter::repeat_n('.', width - len + 1));
+        padded.push(row);
     }
-    let blank_line = ".".repeat(lines[0].len());
-    lines.insert(0, blank_line.clone());
-    lines.push(blank_line);
+    padded.push(vec!['.'; width + 2]);
 
-    Ok(Grid { lines })
+    Ok(Grid {
+        rows: padded,
+        line_lens,
+    })
 }
 
 struct Grid {
-    /// Padded with '.'s around the edges.
-    lines: Vec<String>,
+    /// Indexed by char, not by byte. Padded with '.'s around the edges.
+    rows: Vec<Vec<char>>,
+    /// The length (in chars) of each input line, before padding.
+    line_lens: Vec<usize>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
+struct ParseOptions {
+    /// Treat a '-' directly before a number as its sign, instead of as a symbol.
+    signed: bool,
+    /// A number touching the end of a line continues at the start of the next one.
+    /// So does a '-' at the end of a line, if it's a sign.
+    wrap_lines: bool,
+}
+
+impl ParseOptions {
+    /// Read `--signed` and `--wrap-lines` from the command line.
+    fn from_args() -> Result<Self> {
+        let mut opts = Self::default();
+        for arg in env::args().skip(1) {
+            match arg.as_str() {
+                "--signed" => opts.signed = true,
+                "--wrap-lines" => opts.wrap_lines = true,
+                _ => bail!("unknown argument: {arg:?}"),
+            }
+        }
+        Ok(opts)
+    }
+}
+
+#[derive(Debug, Clone)]
 struct Number {
-    value: u32,
-    start: Point,
-    len: usize,
+    value: i64,
+    /// Every cell covered by the number, including its sign.
+    cells: Vec<Point>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -62,55 +98,116 @@ struct Point {
 }
 
 impl Grid {
-    fn find_numbers(&self) -> Result<Vec<Number>> {
+    /// The cells of the original input, in reading order.
+    ///
+    /// A line break is represented by `None`, unless lines wrap.
+    fn reading_order(&self, wrap_lines: bool) -> impl Iterator<Item = Option<Point>> + '_ {
+        self.line_lens
+            .iter()
+            .enumerate()
+            .flat_map(move |(i, &len)| {
+                let row = i + 1;
+                let cells = (1..=len).map(move |col| Some(Point { row, col }));
+                let line_break = if wrap_lines { None } else { Some(None) };
+                cells.chain(line_break)
+            })
+    }
+
+    fn find_numbers(&self, opts: ParseOptions) -> Result<Vec<Number>> {
         let mut numbers = vec![];
 
-        for (row, l) in self.lines.iter().enumerate() {
-            static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\d+)").unwrap());
+        // The cells of the number we're currently in the middle of.
+        let mut cells: Vec<Point> = vec![];
+        let mut text = String::new();
+
+        for p in self.reading_order(opts.wrap_lines).chain([None]) {
+            let c = p.map(|p| self.get(p));
+            if let (Some(p), Some(c)) = (p, c) {
+                if c.is_ascii_digit() {
+                    if cells.is_empty() && opts.signed {
+                        if let Some(sign) = self.sign_before(p, opts.wrap_lines) {
+                            cells.push(sign);
+                            text.push('-');
+                        }
+                    }
+                    cells.push(p);
+                    text.push(c);
+                    continue;
+                }
+            }
 
-            for match_ in RE.find_iter(l) {
-                let value = match_.as_str().parse().context("number value")?;
-                let n = Number {
+            if !cells.is_empty() {
+                let value = text.parse().context("number value")?;
+                numbers.push(Number {
                     value,
-                    start: Point {
-                        row,
-                        col: match_.start(),
-                    },
-                    len: match_.len(),
-                };
-                numbers.push(n);
+                    cells: std::mem::take(&mut cells),
+                });
+                text.clear();
             }
         }
 
         Ok(numbers)
     }
 
-    fn is_part_number(&self, n: Number) -> bool {
-        let left = Point {
-            row: n.start.row,
-            col: n.start.col - 1,
-        };
-        let right = Point {
-            row: n.start.row,
-            col: n.start.col + n.len,
-        };
+    /// If the number starting at `p` is preceded by a minus sign, return the
+    /// sign's location.
+    ///
+    /// A '-' between two numbers (e.g. "12-34") is a symbol, not a sign.
+    fn sign_before(&self, p: Point, wrap_lines: bool) -> Option<Point> {
+        let sign = self.cell_before(p, wrap_lines)?;
+        if self.get(sign) != '-' {
+            return None;
+        }
+        let before_sign = self.cell_before(sign, wrap_lines);
+        if before_sign.is_some_and(|q| self.get(q).is_ascii_digit()) {
+            return None;
+        }
+        Some(sign)
+    }
 
-        let start = n.start.col - 1;
-        let end = n.start.col + n.len + 1;
-        let above = &self.lines[n.start.row - 1][start..end];
-        let below = &self.lines[n.start.row + 1][start..end];
+    /// The cell before `p` in reading order. If lines wrap, that can be the
+    /// end of an earlier line, skipping over empty ones.
+    fn cell_before(&self, p: Point, wrap_lines: bool) -> Option<Point> {
+        if p.col > 1 {
+            return Some(Point {
+                row: p.row,
+                col: p.col - 1,
+            });
+        }
+        if !wrap_lines {
+            return None;
+        }
+        (1..p.row).rev().find_map(|row| {
+            let len = self.line_lens[row - 1];
+            (len > 0).then_some(Point { row, col: len })
+        })
+    }
 
-        is_symbol(self.get(left))
-            || is_symbol(self.get(right))
-            || above.chars().any(is_symbol)
-            || below.chars().any(is_symbol)
+    fn is_part_number(&self, n: &Number) -> bool {
+        n.cells
+            .iter()
+            .flat_map(|&p| neighbors(p))
+            .filter(|p| !n.cells.contains(p))
+            .any(|p| is_symbol(self.get(p)))
     }
 
     fn get(&self, p: Point) -> char {
-        self.lines[p.row][p.col..].chars().next().unwrap()
+        self.rows[p.row][p.col]
     }
 }
 
+/// The 8 points surrounding p.
+fn neighbors(p: Point) -> impl Iterator<Item = Point> {
+    let top_left = Point {
+        row: p.row - 1,
+        col: p.col - 1,
+    };
+    (0..3)
+        .cartesian_product(0..3)
+        .filter(|&rc| rc != (1, 1))
+        .map(move |(row, col)| top_left + Point { row, col })
+}
+
 fn is_symbol(c: char) -> bool {
     c != '.' && !c.is_ascii_digit()
 }
@@ -118,7 +215,7 @@ fn is_symbol(c: char) -> bool {
 /// Part 2
 fn main() -> Result<()> {
     let grid = read_grid()?;
-    let numbers = grid.find_numbers()?;
+    let numbers = grid.find_numbers(ParseOptions::from_args()?)?;
 
     let map = number_map(&numbers);
 
@@ -139,11 +236,7 @@ type NumberMap = HashMap<Point, usize>;
 fn number_map(numbers: &[Number]) -> NumberMap {
     let mut map = HashMap::new();
     for (id, n) in numbers.iter().enumerate() {
-        for col in n.start.col..n.start.col + n.len {
-            let p = Point {
-                row: n.start.row,
-                col,
-            };
+        for &p in &n.cells {
             map.insert(p, id);
         }
     }
@@ -153,8 +246,8 @@ fn number_map(numbers: &[Number]) -> NumberMap {
 impl Grid {
     fn find_gears(&self, map: &NumberMap) -> Vec<Gear> {
         let mut gears = vec![];
-        for (row, l) in self.lines.iter().enumerate() {
-            for (col, c) in l.chars().enumerate() {
+        for (row, l) in self.rows.iter().enumerate() {
+            for (col, &c) in l.iter().enumerate() {
                 if c == '*' {
                     let nbrs = self.neighboring_numbers(Point { row, col }, map);
                     if nbrs.len() == 2 {
@@ -170,20 +263,11 @@ impl Grid {
     /// Return a set of number ids.
     fn neighboring_numbers(&self, p: Point, map: &NumberMap) -> HashSet<usize> {
         let mut nbrs = HashSet::new();
-
-        let top_left = Point {
-            row: p.row - 1,
-            col: p.col - 1,
-        };
-        for row in [0, 1, 2] {
-            for col in [0, 1, 2] {
-                let p2 = top_left + Point { row, col };
-                if let Some(&id) = map.get(&p2) {
-                    nbrs.insert(id);
-                }
+        for p2 in neighbors(p) {
+            if let Some(&id) = map.get(&p2) {
+                nbrs.insert(id);
             }
         }
-
         nbrs
     }
 }
@@ -203,3 +287,87 @@ impl Add for Point {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIGNED: ParseOptions = ParseOptions {
+        signed: true,
+        wrap_lines: false,
+    };
+    const WRAP: ParseOptions = ParseOptions {
+        signed: false,
+        wrap_lines: true,
+    };
+    const BOTH: ParseOptions = ParseOptions {
+        signed: true,
+        wrap_lines: true,
+    };
+
+    fn values(s: &str, opts: ParseOptions) -> Result<Vec<i64>> {
+        let numbers = parse_grid(s)?.find_numbers(opts)?;
+        Ok(numbers.iter().map(|n| n.value).collect())
+    }
+
+    #[test]
+    fn signed() -> Result<()> {
+        let s = "-12..-3\n4-5.-6-";
+        assert_eq!(values(s, ParseOptions::default())?, [12, 3, 4, 5, 6]);
+        // "4-5" is a subtraction, not a negative 5.
+        assert_eq!(values(s, SIGNED)?, [-12, -3, 4, 5, -6]);
+        Ok(())
+    }
+
+    #[test]
+    fn wrapped() -> Result<()> {
+        let s = "..12\n34..\n5";
+        assert_eq!(values(s, ParseOptions::default())?, [12, 34, 5]);
+        assert_eq!(values(s, WRAP)?, [1234, 5]);
+
+        let grid = parse_grid(s)?;
+        let numbers = grid.find_numbers(WRAP)?;
+        assert_eq!(numbers[0].cells.len(), 4);
+        assert!(!grid.is_part_number(&numbers[0]));
+        Ok(())
+    }
+
+    /// Short lines are padded with '.'s, which break numbers even when lines
+    /// wrap.
+    #[test]
+    fn ragged() -> Result<()> {
+        let s = "1\n..23\n4*\n\n56";
+        assert_eq!(values(s, ParseOptions::default())?, [1, 23, 4, 56]);
+        // The empty line has no cells at all.
+        assert_eq!(values(s, WRAP)?, [1, 234, 56]);
+
+        let grid = parse_grid(s)?;
+        assert_eq!(grid.rows[1], ['.', '1', '.', '.', '.', '.']);
+        let numbers = grid.find_numbers(WRAP)?;
+        let parts = numbers.iter().map(|n| grid.is_part_number(n)).collect_vec();
+        assert_eq!(parts, [false, true, false]);
+        Ok(())
+    }
+
+    /// A '-' at the end of a line is the sign of a number that starts the next.
+    #[test]
+    fn wrapped_sign() -> Result<()> {
+        let s = "..-\n12.";
+        assert_eq!(values(s, SIGNED)?, [12]);
+        assert_eq!(values(s, BOTH)?, [-12]);
+
+        let grid = parse_grid(s)?;
+        let numbers = grid.find_numbers(BOTH)?;
+        assert_eq!(numbers[0].cells[0], Point { row: 1, col: 3 });
+        assert!(!grid.is_part_number(&numbers[0]));
+        // Without the sign, the '-' is a symbol touching the 12.
+        let numbers = grid.find_numbers(SIGNED)?;
+        assert!(grid.is_part_number(&numbers[0]));
+
+        // Across empty lines, too; but not after a digit.
+        assert_eq!(values("-\n\n7", BOTH)?, [-7]);
+        assert_eq!(values("3-\n4", BOTH)?, [3, 4]);
+        assert_eq!(values("3\n-4", BOTH)?, [3, 4]);
+        Ok(())
+    }
+}
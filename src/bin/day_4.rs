@@ -1,6 +1,8 @@
-use std::{collections::HashSet, io, result::Result as StdResult};
+use std::{collections::HashSet, env, io, result::Result as StdResult, str::FromStr};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, ensure, Context, Result};
+use itertools::Itertools;
+use num_bigint::BigUint;
 
 #[allow(dead_code)]
 fn part_1() -> Result<()> {
@@ -56,6 +58,13 @@ impl Card {
         count
     }
 
+    fn matching_numbers(&self) -> impl Iterator<Item = u32> + '_ {
+        self.numbers_you_have
+            .iter()
+            .copied()
+            .filter(|n| self.winning_numbers.contains(n))
+    }
+
     fn score_part_1(&self) -> u32 {
         let count = self.num_winning();
         if count != 0 {
@@ -67,18 +76,337 @@ impl Card {
 }
 
 fn main() -> Result<()> {
+    let args = Args::parse()?;
     let cards = read_cards()?;
-    let n = cards.len();
-    let mut freqs = vec![1; n];
 
-    for (i, c) in cards.into_iter().enumerate() {
-        for j in 1..=c.num_winning() as usize {
-            freqs[i + j] += freqs[i];
+    let scores = match args.rule {
+        Rule::Cascade { span } => {
+            let cascade = Cascade::run(&cards, span, args.overflow, args.breakdown)?;
+            if args.breakdown {
+                cascade.print_breakdown();
+            }
+            cascade.scores()
+        }
+        rule => rule.scores(&cards, args.overflow)?,
+    };
+    if args.scores {
+        for (i, score) in scores.iter().enumerate() {
+            println!("Card {}: {score}", i + 1);
         }
     }
-
-    let total: u32 = freqs.into_iter().sum();
+    let total: BigUint = scores.iter().sum();
     dbg!(total);
 
     Ok(())
 }
+
+struct Args {
+    rule: Rule,
+    overflow: Overflow,
+    breakdown: bool,
+    scores: bool,
+}
+
+impl Args {
+    /// Usage: `day_4 [--rule=RULE] [--overflow=error|clamp|wrap] [--breakdown] [--scores]`
+    ///
+    /// See [`Rule`] for the syntax of RULE. `--breakdown` only makes sense
+    /// for cascades.
+    fn parse() -> Result<Self> {
+        Self::parse_from(env::args().skip(1))
+    }
+
+    fn parse_from(raw: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut args = Self {
+            rule: Rule::Cascade { span: 1 },
+            overflow: Overflow::Error,
+            breakdown: false,
+            scores: false,
+        };
+        for arg in raw {
+            if let Some(rule) = arg.strip_prefix("--rule=") {
+                args.rule = rule.parse()?;
+            } else if let Some(policy) = arg.strip_prefix("--overflow=") {
+                args.overflow = policy.parse()?;
+            } else if arg == "--breakdown" {
+                args.breakdown = true;
+            } else if arg == "--scores" {
+                args.scores = true;
+            } else {
+                bail!("unknown argument: {arg:?}");
+            }
+        }
+        ensure!(
+            !args.breakdown || matches!(args.rule, Rule::Cascade { .. }),
+            "--breakdown needs a cascade rule, not {:?}",
+            args.rule
+        );
+        Ok(args)
+    }
+}
+
+/// What to do when a card wins copies of cards past the end of the table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Overflow {
+    /// Fail with an error.
+    Error,
+    /// Discard any copies past the last card.
+    Clamp,
+    /// Continue counting from the first card.
+    ///
+    /// Each card is still only processed once, in order. So copies won of a
+    /// card that has already been processed are counted, but don't win any
+    /// further copies.
+    Wrap,
+}
+
+impl FromStr for Overflow {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "error" => Self::Error,
+            "clamp" => Self::Clamp,
+            "wrap" => Self::Wrap,
+            _ => bail!("unknown overflow policy: {s:?}"),
+        })
+    }
+}
+
+struct Cascade {
+    /// The number of copies of each card, including the original.
+    totals: Vec<BigUint>,
+    /// `by_origin[i][o]` is the number of copies of card i that were won
+    /// (directly or indirectly) by the original instance of card o.
+    ///
+    /// The original instance of card i counts as one of these, with o = i.
+    /// This is O(n^2), so it's only kept when asked for.
+    by_origin: Option<Vec<Vec<BigUint>>>,
+}
+
+impl Cascade {
+    /// A card with m matches wins copies of the next `m * span` cards.
+    fn run(cards: &[Card], span: usize, overflow: Overflow, breakdown: bool) -> Result<Self> {
+        let n = cards.len();
+        let mut totals = vec![BigUint::from(1u32); n];
+        let mut by_origin = breakdown.then(|| {
+            let mut rows = vec![vec![BigUint::ZERO; n]; n];
+            for (i, row) in rows.iter_mut().enumerate() {
+                row[i] = BigUint::from(1u32);
+            }
+            rows
+        });
+
+        for (i, c) in cards.iter().enumerate() {
+            // Every copy of card i wins a copy of each target card. Count them
+            // before card i wins anything, since any copies it wins of itself
+            // (when wrapping) don't win any further copies.
+            let won = totals[i].clone();
+            let won_by_origin = by_origin.as_ref().map(|rows| rows[i].clone());
+
+            for j in 1..=c.num_winning() as usize * span {
+                let target = match overflow {
+                    _ if i + j < n => i + j,
+                    Overflow::Error => bail!(
+                        "card {} wins a copy of card {}, but there are only {n} cards",
+                        i + 1,
+                        i + j + 1,
+                    ),
+                    Overflow::Clamp => break,
+                    Overflow::Wrap => (i + j) % n,
+                };
+
+                totals[target] += &won;
+                if let (Some(rows), Some(won)) = (&mut by_origin, &won_by_origin) {
+                    for (dst, src) in rows[target].iter_mut().zip(won) {
+                        *dst += src;
+                    }
+                }
+            }
+        }
+
+        Ok(Self { totals, by_origin })
+    }
+
+    /// Each card's number of copies.
+    fn scores(self) -> Vec<BigUint> {
+        self.totals
+    }
+
+    /// Needs the cascade to have been run with `breakdown` set.
+    fn print_breakdown(&self) {
+        let rows = self.by_origin.as_ref().expect("no breakdown");
+        for (i, row) in rows.iter().enumerate() {
+            let sources = row
+                .iter()
+                .enumerate()
+                .filter(|(_, count)| **count != BigUint::ZERO)
+                .map(|(o, count)| format!("{count} from card {}", o + 1))
+                .join(", ");
+            println!("Card {}: {} copies ({sources})", i + 1, self.totals[i]);
+        }
+    }
+}
+
+/// A way of scoring scratchcards.
+///
+/// Written as `name` or `name:param`:
+/// - `geometric[:base]`: the first match is worth 1 point, and each further
+///   match multiplies the score by `base` (default 2). This is part 1.
+/// - `linear[:points]`: each match is worth `points` (default 1).
+/// - `value`: each match is worth its own number.
+/// - `cascade[:span]`: a card with m matches wins copies of the next
+///   `m * span` cards (default 1), and scores its number of copies. This is
+///   part 2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rule {
+    Geometric { base: u32 },
+    Linear { points: u32 },
+    Value,
+    Cascade { span: usize },
+}
+
+impl FromStr for Rule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, param) = match s.split_once(':') {
+            Some((name, param)) => (name, Some(param)),
+            None => (s, None),
+        };
+        let parse_param = |default| -> Result<u32> {
+            match param {
+                Some(p) => p.parse().with_context(|| format!("rule parameter {p:?}")),
+                None => Ok(default),
+            }
+        };
+
+        Ok(match name {
+            "geometric" => Self::Geometric {
+                base: parse_param(2)?,
+            },
+            "linear" => Self::Linear {
+                points: parse_param(1)?,
+            },
+            "value" => {
+                ensure!(param.is_none(), "rule `value` takes no parameter");
+                Self::Value
+            }
+            "cascade" => Self::Cascade {
+                span: parse_param(1)? as usize,
+            },
+            _ => bail!("unknown rule: {name:?}"),
+        })
+    }
+}
+
+impl Rule {
+    /// The score of each card, in order.
+    ///
+    /// The overflow policy only matters for cascades.
+    fn scores(self, cards: &[Card], overflow: Overflow) -> Result<Vec<BigUint>> {
+        let scores = match self {
+            Self::Geometric { base } => cards
+                .iter()
+                .map(|c| match c.num_winning() {
+                    0 => BigUint::ZERO,
+                    count => BigUint::from(base).pow(count - 1),
+                })
+                .collect(),
+            Self::Linear { points } => cards
+                .iter()
+                .map(|c| BigUint::from(points) * c.num_winning())
+                .collect(),
+            Self::Value => cards
+                .iter()
+                .map(|c| c.matching_numbers().map(BigUint::from).sum())
+                .collect(),
+            Self::Cascade { span } => Cascade::run(cards, span, overflow, false)?.scores(),
+        };
+        Ok(scores)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: [&str; 6] = [
+        "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53",
+        "Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19",
+        "Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1",
+        "Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 66 77",
+        "Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36",
+        "Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11",
+    ];
+
+    fn total(rule: &str, overflow: Overflow) -> Result<BigUint> {
+        let cards: Vec<_> = SAMPLE.iter().map(|l| read_card(l)).try_collect()?;
+        let scores = rule.parse::<Rule>()?.scores(&cards, overflow)?;
+        Ok(scores.iter().sum())
+    }
+
+    #[test]
+    fn sample() -> Result<()> {
+        assert_eq!(total("geometric", Overflow::Error)?, 13u32.into());
+        assert_eq!(total("cascade", Overflow::Error)?, 30u32.into());
+        assert!(total("cascade:2", Overflow::Error).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn breakdown_needs_a_cascade() {
+        let args = |list: &[&str]| Args::parse_from(list.iter().map(|s| s.to_string()));
+        assert!(args(&["--breakdown"]).is_ok());
+        assert!(args(&["--rule=cascade:3", "--breakdown"]).is_ok());
+        assert!(args(&["--rule=linear", "--breakdown"]).is_err());
+        assert!(args(&["--breakdown", "--rule=value"]).is_err());
+    }
+
+    /// The breakdown comes from the same cascade as the scores, span and all.
+    #[test]
+    fn breakdown_uses_the_span() -> Result<()> {
+        let cards: Vec<_> = SAMPLE.iter().map(|l| read_card(l)).try_collect()?;
+        let cascade = Cascade::run(&cards, 2, Overflow::Clamp, true)?;
+        let rows = cascade.by_origin.clone().unwrap();
+        let scores = Rule::Cascade { span: 2 }.scores(&cards, Overflow::Clamp)?;
+        for (row, score) in rows.iter().zip(&scores) {
+            assert_eq!(row.iter().sum::<BigUint>(), *score);
+        }
+        assert_eq!(cascade.scores(), scores);
+        assert_ne!(
+            scores,
+            Cascade::run(&cards, 1, Overflow::Clamp, false)?.scores()
+        );
+        Ok(())
+    }
+
+    /// Card 1 wins the next 8 cards: 2 to 6, then itself, then 2 and 3 again.
+    /// The copy of itself that it wins doesn't win anything more, so it only
+    /// contributes 2 copies of card 2.
+    #[test]
+    fn wrapped_copies_dont_win() -> Result<()> {
+        let cards: Vec<_> = SAMPLE.iter().map(|l| read_card(l)).try_collect()?;
+        let rows = Cascade::run(&cards, 2, Overflow::Wrap, true)?
+            .by_origin
+            .unwrap();
+        let from_card_1: Vec<u32> = rows
+            .iter()
+            .map(|row| row[0].clone().try_into().unwrap())
+            .collect();
+        assert_eq!(from_card_1, [6, 2, 4, 7, 14, 14]);
+        Ok(())
+    }
+
+    /// Only the totals are kept by default, so big inputs are fine.
+    #[test]
+    fn many_cards() -> Result<()> {
+        let cards: Vec<_> = (0..20_000)
+            .map(|_| read_card("Card 1: 1 2 | 1 3"))
+            .try_collect()?;
+        let scores = Cascade::run(&cards, 1, Overflow::Clamp, false)?.scores();
+        assert_eq!(scores[0], 1u32.into());
+        assert_eq!(scores[19_999], 20_000u32.into());
+        Ok(())
+    }
+}
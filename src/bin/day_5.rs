@@ -1,5 +1,21 @@
-use anyhow::Result;
+use std::env;
 
+use anyhow::{bail, Result};
+
+/// Usage: `day_5 [FROM TO VALUE | preimage [CATEGORY] FIRST LAST | --composed | --inverses]`
 fn main() -> Result<()> {
-    advent_2023::day_5::solve()
+    let args: Vec<_> = env::args().skip(1).collect();
+    match &args[..] {
+        [] => advent_2023::day_5::solve(),
+        [flag] if flag == "--composed" => advent_2023::day_5::composed(),
+        [flag] if flag == "--inverses" => advent_2023::day_5::inverses(),
+        [cmd, first, last] if cmd == "preimage" => {
+            advent_2023::day_5::preimage("seed", first.parse()?, last.parse()?)
+        }
+        [cmd, category, first, last] if cmd == "preimage" => {
+            advent_2023::day_5::preimage(category, first.parse()?, last.parse()?)
+        }
+        [from, to, x] => advent_2023::day_5::query(from, to, x.parse()?),
+        _ => bail!("usage: day_5 [FROM TO VALUE | preimage [CATEGORY] FIRST LAST | --composed | --inverses]"),
+    }
 }
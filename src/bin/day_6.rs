@@ -1,20 +1,15 @@
-use anyhow::Result;
+use std::io;
 
-const INPUTS: [(u32, u32); 4] = [(50, 242), (74, 1017), (86, 1691), (85, 1252)];
+use anyhow::{ensure, Context, Result};
+use num_bigint::BigUint;
 
 #[allow(dead_code)]
 fn part_1() -> Result<()> {
-    let mut total = 1;
-
-    for (time, distance) in INPUTS {
-        let mut ans = 0;
-        for t in 0..=time {
-            if t * (time - t) > distance {
-                ans += 1;
-            }
-        }
+    let input = read_input()?;
 
-        total *= ans;
+    let mut total = BigUint::from(1u32);
+    for race in input.races()? {
+        total *= race.num_ways_to_win();
     }
 
     dbg!(total);
@@ -22,16 +17,181 @@ fn part_1() -> Result<()> {
 }
 
 fn main() -> Result<()> {
-    let time = 50_74_86_85;
-    let distance = 242_1017_1691_1252;
+    let input = read_input()?;
+    let race = input.kerned_race()?;
 
-    let mut ans = 0;
-    for t in 0u64..=time {
-        if t * (time - t) > distance {
-            ans += 1;
-        }
-    }
+    let ans = race.num_ways_to_win();
     dbg!(ans);
 
     Ok(())
 }
+
+/// The numbers from each line, as written.
+struct Input {
+    times: Vec<String>,
+    distances: Vec<String>,
+}
+
+fn read_input() -> Result<Input> {
+    let text = io::read_to_string(io::stdin())?;
+    let mut lines = text.lines();
+
+    let times = parse_line(lines.next().context("time line")?, "Time:")?;
+    let distances = parse_line(lines.next().context("distance line")?, "Distance:")?;
+    ensure!(times.len() == distances.len(), "length mismatch");
+
+    Ok(Input { times, distances })
+}
+
+fn parse_line(line: &str, prefix: &str) -> Result<Vec<String>> {
+    let nums = line.strip_prefix(prefix).context(prefix.to_string())?;
+    let nums: Vec<_> = nums.split_whitespace().map(String::from).collect();
+    for n in &nums {
+        ensure!(n.chars().all(|c| c.is_ascii_digit()), "not a number: {n:?}");
+    }
+    Ok(nums)
+}
+
+#[derive(Debug, Clone)]
+struct Race {
+    time: BigUint,
+    /// The record distance to beat.
+    distance: BigUint,
+}
+
+impl Input {
+    /// Part 1: each column is a separate race.
+    fn races(&self) -> Result<Vec<Race>> {
+        let mut races = vec![];
+        for (t, d) in self.times.iter().zip(&self.distances) {
+            races.push(Race {
+                time: t.parse()?,
+                distance: d.parse()?,
+            });
+        }
+        Ok(races)
+    }
+
+    /// Part 2: there's only one race; ignore the spaces between numbers.
+    fn kerned_race(&self) -> Result<Race> {
+        ensure!(!self.times.is_empty(), "no races");
+        Ok(Race {
+            time: self.times.concat().parse()?,
+            distance: self.distances.concat().parse()?,
+        })
+    }
+}
+
+impl Race {
+    /// Holding the button for `t` ms travels `t * (time - t)` mm. We want to
+    /// count the integers `t` in `0..=time` for which this beats the record.
+    ///
+    /// The winning hold times lie strictly between the roots of
+    /// `t^2 - time*t + distance = 0`, namely `(time ± sqrt(time^2 - 4*distance)) / 2`.
+    /// We estimate the lower root with an integer square root, which is at
+    /// most one below the exact boundary.
+    fn num_ways_to_win(&self) -> BigUint {
+        let time = &self.time;
+        let four_d = &self.distance * 4u32;
+        let time_sq = time * time;
+        if time_sq <= four_d {
+            // The best possible distance, (time/2)^2, doesn't beat the record.
+            return BigUint::ZERO;
+        }
+
+        // Since disc <= time^2, we have root <= time.
+        let disc = time_sq - four_d;
+        if disc == BigUint::from(1u32) {
+            // Only possible for odd times, where the best whole hold time is
+            // `(time - 1) / 2`. That travels `(time^2 - 1) / 4`, which is
+            // exactly the record.
+            return BigUint::ZERO;
+        }
+        let root = disc.sqrt();
+        let mut lo = (time - &root) / 2u32;
+
+        let one = BigUint::from(1u32);
+        if !self.wins(&lo) {
+            lo += &one;
+        }
+        if lo > BigUint::ZERO && self.wins(&(&lo - &one)) {
+            lo -= &one;
+        }
+        assert!(self.wins(&lo), "no winning hold time near {lo}");
+
+        // The winning hold times are symmetric about time/2.
+        let hi = time - &lo;
+        hi - lo + one
+    }
+
+    fn wins(&self, hold: &BigUint) -> bool {
+        hold * (&self.time - hold) > self.distance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn race(time: &str, distance: &str) -> Race {
+        Race {
+            time: time.parse().unwrap(),
+            distance: distance.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn sample() -> Result<()> {
+        let input = Input {
+            times: parse_line("Time:      7  15   30", "Time:")?,
+            distances: parse_line("Distance:  9  40  200", "Distance:")?,
+        };
+        let product: BigUint = input.races()?.iter().map(Race::num_ways_to_win).product();
+        assert_eq!(product, 288u32.into());
+        assert_eq!(input.kerned_race()?.num_ways_to_win(), 71503u32.into());
+        Ok(())
+    }
+
+    #[test]
+    fn no_winners() {
+        // The best hold is 1 or 2 ms, which only ties the record.
+        assert_eq!(race("3", "2").num_ways_to_win(), BigUint::ZERO);
+        assert_eq!(race("4", "4").num_ways_to_win(), BigUint::ZERO);
+        assert_eq!(race("0", "0").num_ways_to_win(), BigUint::ZERO);
+        assert_eq!(race("3", "1").num_ways_to_win(), 2u32.into());
+    }
+
+    #[test]
+    fn big_numbers() {
+        let t = BigUint::from(10u32).pow(30);
+
+        // An odd time whose best hold only ties the record.
+        let odd = &t + 1u32;
+        let d = (&odd * &odd - 1u32) / 4u32;
+        let r = Race {
+            time: odd,
+            distance: d.clone(),
+        };
+        assert_eq!(r.num_ways_to_win(), BigUint::ZERO);
+        let r = Race {
+            time: r.time,
+            distance: d - 1u32,
+        };
+        assert_eq!(r.num_ways_to_win(), 2u32.into());
+
+        // Only the exact midpoint wins.
+        let d = &t * &t / 4u32 - 1u32;
+        let r = Race {
+            time: t.clone(),
+            distance: d,
+        };
+        assert_eq!(r.num_ways_to_win(), 1u32.into());
+
+        // Anything but 0 and `t` wins.
+        let r = Race {
+            time: t.clone(),
+            distance: BigUint::ZERO,
+        };
+        assert_eq!(r.num_ways_to_win(), t - 1u32);
+    }
+}
@@ -1,12 +1,36 @@
-use std::{cmp::Ordering, collections::HashMap, io};
+use std::{cmp::Ordering, collections::HashMap, env, fmt, fs, io};
 
-use anyhow::{anyhow, bail, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
 use itertools::Itertools;
 
+/// Usage:
+/// - `day_7 [--explain] [RULES]`
+/// - `day_7 compare HAND HAND [RULES]`
+///
+/// RULES is either the name of a built-in rule set (`standard` for part 1,
+/// `jokers` for part 2), or the path to a rule set definition file. See
+/// [`RuleSet::parse`] for the file format.
 fn main() -> Result<()> {
-    let mut hands = read_input()?;
-    // hands.sort_unstable();
-    hands.sort_unstable_by_key(|&(h, _)| WildCardRules(h));
+    let args: Vec<_> = env::args().skip(1).collect();
+    let args: Vec<_> = args.iter().map(String::as_str).collect();
+    match args[..] {
+        ["compare", a, b] => compare(a, b, &RuleSet::jokers()),
+        ["compare", a, b, rules] => compare(a, b, &RuleSet::from_name_or_file(rules)?),
+        ["--explain"] => solve(&RuleSet::jokers(), true),
+        ["--explain", rules] => solve(&RuleSet::from_name_or_file(rules)?, true),
+        [] => solve(&RuleSet::jokers(), false),
+        [rules] => solve(&RuleSet::from_name_or_file(rules)?, false),
+        _ => bail!("usage: day_7 [--explain] [RULES] | day_7 compare HAND HAND [RULES]"),
+    }
+}
+
+fn solve(rules: &RuleSet, explain: bool) -> Result<()> {
+    let mut hands = read_input(rules)?;
+    hands.sort_by(|(a, _), (b, _)| rules.cmp(a, b));
+
+    if explain {
+        explain_ranking(&hands, rules);
+    }
 
     let mut total_winnings = 0;
     for (i, (_, bid)) in (1..).zip(hands) {
@@ -17,28 +41,74 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// Print each hand in rank order, and why it ranks where it does relative to
+/// its neighbours.
+fn explain_ranking(sorted_hands: &[Line], rules: &RuleSet) {
+    for (i, (hand, bid)) in sorted_hands.iter().enumerate() {
+        println!(
+            "rank {}: {hand} (bid {bid}) is {}, with freqs {:?}",
+            i + 1,
+            rules.category(hand),
+            rules.freqs(hand),
+        );
+        if let Some((weaker, _)) = i.checked_sub(1).map(|j| &sorted_hands[j]) {
+            println!("  beats {weaker}: {}", rules.explain(hand, weaker));
+        }
+        if let Some((stronger, _)) = sorted_hands.get(i + 1) {
+            println!("  loses to {stronger}: {}", rules.explain(hand, stronger));
+        }
+    }
+}
+
+fn compare(a: &str, b: &str, rules: &RuleSet) -> Result<()> {
+    let a = parse_hand(a, rules)?;
+    let b = parse_hand(b, rules)?;
+    for hand in [&a, &b] {
+        println!(
+            "{hand} is {}, with freqs {:?}",
+            rules.category(hand),
+            rules.freqs(hand),
+        );
+    }
+    let verdict = match rules.cmp(&a, &b) {
+        Ordering::Less => "loses to",
+        Ordering::Equal => "ties with",
+        Ordering::Greater => "beats",
+    };
+    println!("{a} {verdict} {b}: {}", rules.explain(&a, &b));
+    Ok(())
+}
+
 type Line = (Hand, u32);
 type Input = Vec<Line>;
 
-fn read_input() -> Result<Input> {
-    io::stdin().lines().map(parse_line).collect()
+fn read_input(rules: &RuleSet) -> Result<Input> {
+    io::stdin()
+        .lines()
+        .map(|line| parse_line(&line?, rules))
+        .collect()
 }
 
-fn parse_line(line: io::Result<String>) -> Result<Line> {
-    let line = line?;
+fn parse_line(line: &str, rules: &RuleSet) -> Result<Line> {
     let (cards, bid) = line
         .split_once(char::is_whitespace)
         .context("split whitespace")?;
-    let hand = parse_hand(cards)?;
+    let hand = parse_hand(cards, rules)?;
     let line = (hand, bid.parse()?);
     Ok(line)
 }
 
-fn parse_hand(cards: &str) -> Result<Hand> {
+/// Every card must be one the rules give a rank to.
+fn parse_hand(cards: &str, rules: &RuleSet) -> Result<Hand> {
     let cards: Vec<_> = cards.chars().map(parse_card).try_collect()?;
-    let cards = cards
-        .try_into()
-        .map_err(|_| anyhow!("hand must contain 5 cards"))?;
+    let hand_size = rules.hand_size;
+    ensure!(
+        cards.len() == hand_size,
+        "hand must contain {hand_size} cards"
+    );
+    if let Some(c) = cards.iter().find(|c| !rules.rank_order.contains(c)) {
+        bail!("{c} isn't in the rank order");
+    }
     Ok(Hand { cards })
 }
 
@@ -63,9 +133,9 @@ fn parse_card(c: char) -> Result<Card> {
     Ok(card)
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Hand {
-    cards: [Card; 5],
+    cards: Vec<Card>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -85,82 +155,189 @@ enum Card {
     A,
 }
 
-impl Ord for Hand {
-    fn cmp(&self, other: &Self) -> Ordering {
-        // Break ties by lex ordering the card string.
-        let key = |hand: &Self| (hand.category(), hand.cards);
-        key(self).cmp(&key(other))
-    }
+/// The type of a hand, e.g. "full house".
+///
+/// Hands are classified by their group sizes, sorted in decreasing order; e.g.
+/// a full house is `[3, 2]`. Comparing these lexicographically gives the usual
+/// ordering of categories, for any hand size.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Category {
+    groups: Vec<u32>,
+    /// A straight ranks just above a three of a kind with no other pairs
+    /// (i.e. just below a full house, for 5-card hands). We represent this as
+    /// a three of a kind with the `straight` flag set.
+    ///
+    /// Smaller hands have no such three of a kind, so straights need at least
+    /// 5 cards.
+    straight: bool,
 }
 
-impl PartialOrd for Hand {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+impl Category {
+    fn from_freqs(freqs: &[u32]) -> Self {
+        let groups = freqs.iter().copied().sorted().rev().collect();
+        Self {
+            groups,
+            straight: false,
+        }
     }
-}
 
-impl Hand {
-    fn category(self) -> Category {
-        let mut freq_map = HashMap::new();
-        for c in self.cards {
-            *freq_map.entry(c).or_default() += 1;
+    fn straight(hand_size: usize) -> Self {
+        assert!(hand_size >= 5, "no straights in {hand_size}-card hands");
+        let mut groups = vec![1; hand_size - 2];
+        groups[0] = 3;
+        Self {
+            groups,
+            straight: true,
         }
-        let freqs: Vec<_> = freq_map.values().copied().collect();
-
-        Category::from_freqs(&freqs)
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-enum Category {
-    HighCard,
-    OnePair,
-    TwoPair,
-    ThreeOfAKind,
-    FullHouse,
-    FourOfAKind,
-    FiveOfAKind,
+/// How to compare two hands of the same category.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TieBreak {
+    /// Compare the cards in the order they were dealt.
+    InOrder,
+    /// Compare the largest group's rank first, then the next group, etc. Ties
+    /// between groups of the same size go to the higher rank, like in poker.
+    ByGroup,
 }
 
-impl Category {
-    fn from_freqs(freqs: &[u32]) -> Self {
-        if freqs.contains(&5) {
-            Category::FiveOfAKind
-        } else if freqs.contains(&4) {
-            Category::FourOfAKind
-        } else if freqs.contains(&3) {
-            if freqs.contains(&2) {
-                Category::FullHouse
-            } else {
-                Category::ThreeOfAKind
+#[derive(Debug, Clone)]
+struct RuleSet {
+    hand_size: usize,
+    /// Cards from weakest to strongest. Hands may only contain these cards.
+    rank_order: Vec<Card>,
+    /// These cards pretend to be whatever makes the hand strongest.
+    wildcards: Vec<Card>,
+    /// Whether `hand_size` cards with consecutive ranks form a straight. Only
+    /// allowed for hands of at least 5 cards.
+    straights: bool,
+    tie_break: TieBreak,
+}
+
+impl RuleSet {
+    /// Part 1.
+    fn standard() -> Self {
+        Self {
+            hand_size: 5,
+            rank_order: "23456789TJQKA"
+                .chars()
+                .map(|c| parse_card(c).unwrap())
+                .collect(),
+            wildcards: vec![],
+            straights: false,
+            tie_break: TieBreak::InOrder,
+        }
+    }
+
+    /// Part 2: jokers are wild, but they're the weakest card for tie-breaks.
+    fn jokers() -> Self {
+        Self {
+            rank_order: "J23456789TQKA"
+                .chars()
+                .map(|c| parse_card(c).unwrap())
+                .collect(),
+            wildcards: vec![Card::J],
+            ..Self::standard()
+        }
+    }
+
+    fn from_name_or_file(arg: &str) -> Result<Self> {
+        match arg {
+            "standard" => Ok(Self::standard()),
+            "jokers" => Ok(Self::jokers()),
+            path => {
+                let text =
+                    fs::read_to_string(path).with_context(|| format!("rule set {path:?}"))?;
+                Self::parse(&text).with_context(|| format!("rule set {path:?}"))
             }
-        } else if freqs.contains(&2) {
-            let num_pairs = freqs.iter().filter(|&&f| f == 2).count();
-            if num_pairs == 2 {
-                Category::TwoPair
-            } else {
-                Category::OnePair
+        }
+    }
+
+    /// A rule set definition file has one `key: value` per line, e.g.:
+    ///
+    /// ```text
+    /// hand_size: 5
+    /// ranks: J23456789TQKA
+    /// wild: J
+    /// straights: false
+    /// tie_break: in_order
+    /// ```
+    ///
+    /// Missing keys default to the `standard` rules. `wild` may list any
+    /// number of cards, or be left empty. `tie_break` is `in_order` or
+    /// `by_group`. Blank lines and lines starting with `#` are ignored.
+    fn parse(text: &str) -> Result<Self> {
+        let mut rules = Self::standard();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once(':').context("key: value")?;
+            let value = value.trim();
+            match key.trim() {
+                "hand_size" => rules.hand_size = value.parse()?,
+                "ranks" => rules.rank_order = value.chars().map(parse_card).try_collect()?,
+                "wild" => rules.wildcards = value.chars().map(parse_card).try_collect()?,
+                "straights" => rules.straights = value.parse()?,
+                "tie_break" => {
+                    rules.tie_break = match value {
+                        "in_order" => TieBreak::InOrder,
+                        "by_group" => TieBreak::ByGroup,
+                        _ => bail!("unknown tie break: {value:?}"),
+                    }
+                }
+                _ => bail!("unknown key: {key:?}"),
             }
-        } else {
-            Category::HighCard
         }
+
+        ensure!(rules.hand_size != 0, "hand size must be positive");
+        ensure!(rules.rank_order.iter().all_unique(), "duplicate rank");
+        ensure!(
+            !rules.straights || rules.hand_size >= 5,
+            "straights need a hand size of at least 5, not {}",
+            rules.hand_size
+        );
+        if let Some(c) = (rules.wildcards.iter()).find(|c| !rules.rank_order.contains(c)) {
+            bail!("wildcard {c} isn't in the rank order");
+        }
+        Ok(rules)
     }
-}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct WildCardRules(Hand);
+    fn is_wild(&self, c: Card) -> bool {
+        self.wildcards.contains(&c)
+    }
+
+    /// The strength of a card in tie-breaks; higher is stronger.
+    ///
+    /// `parse_hand` only accepts cards in the rank order.
+    fn rank(&self, c: Card) -> usize {
+        (self.rank_order.iter())
+            .position(|&r| r == c)
+            .expect("card not in the rank order")
+    }
 
-impl WildCardRules {
-    fn category(self) -> Category {
-        Category::from_freqs(&self.freqs())
+    fn cmp(&self, a: &Hand, b: &Hand) -> Ordering {
+        let key = |hand: &Hand| (self.category(hand), self.tie_break_key(hand));
+        key(a).cmp(&key(b))
     }
 
-    fn freqs(self) -> Vec<u32> {
-        let mut num_jokers = 0;
+    fn category(&self, hand: &Hand) -> Category {
+        let groups = Category::from_freqs(&self.freqs(hand));
+        if self.straights && self.can_make_straight(hand) {
+            let straight = Category::straight(self.hand_size);
+            return groups.max(straight);
+        }
+        groups
+    }
+
+    /// How often each card appears in the hand, after wildcard substitution.
+    fn freqs(&self, hand: &Hand) -> Vec<u32> {
+        let mut num_wild = 0;
         let mut other_cards = HashMap::new();
-        for c in self.0.cards {
-            if c == Card::J {
-                num_jokers += 1;
+        for &c in &hand.cards {
+            if self.is_wild(c) {
+                num_wild += 1;
             } else {
                 *other_cards.entry(c).or_default() += 1;
             }
@@ -168,45 +345,215 @@ impl WildCardRules {
 
         let mut freqs: Vec<_> = other_cards.values().copied().sorted().collect();
 
-        // All jokers.
+        // All wild.
         if freqs.is_empty() {
-            return vec![5; 1];
+            return vec![num_wild];
         }
 
-        // Jokers pretend to be the most frequent non-joker card.
-        *freqs.last_mut().unwrap() += num_jokers;
+        // Wildcards pretend to be the most frequent non-wild card.
+        *freqs.last_mut().unwrap() += num_wild;
 
         freqs
     }
+
+    /// Whether the non-wild cards are distinct, and all fit into a run of
+    /// `hand_size` consecutive ranks.
+    fn can_make_straight(&self, hand: &Hand) -> bool {
+        let ranks: Vec<_> = hand
+            .cards
+            .iter()
+            .filter(|&&c| !self.is_wild(c))
+            .map(|&c| self.rank(c))
+            .collect();
+        if !ranks.iter().all_unique() || self.rank_order.len() < self.hand_size {
+            return false;
+        }
+        match ranks.iter().minmax().into_option() {
+            Some((lo, hi)) => hi - lo < self.hand_size,
+            None => true,
+        }
+    }
+
+    /// Card strengths, in the order to compare them.
+    fn tie_break_key(&self, hand: &Hand) -> Vec<usize> {
+        self.tie_break_order(hand)
+            .into_iter()
+            .map(|c| self.rank(c))
+            .collect()
+    }
+
+    /// The hand's cards, in the order to compare them.
+    fn tie_break_order(&self, hand: &Hand) -> Vec<Card> {
+        match self.tie_break {
+            TieBreak::InOrder => hand.cards.clone(),
+            TieBreak::ByGroup => {
+                let counts = hand.cards.iter().counts();
+                let mut cards = hand.cards.clone();
+                cards.sort_by_key(|c| (counts[c], self.rank(*c)));
+                cards.reverse();
+                cards
+            }
+        }
+    }
+
+    /// What decides the comparison between two hands.
+    fn explain(&self, a: &Hand, b: &Hand) -> Decision {
+        let (cat_a, cat_b) = (self.category(a), self.category(b));
+        if cat_a != cat_b {
+            return Decision::Category(cat_a, cat_b);
+        }
+
+        let cards = self
+            .tie_break_order(a)
+            .into_iter()
+            .zip(self.tie_break_order(b));
+        for (i, (x, y)) in cards.enumerate() {
+            if self.rank(x) != self.rank(y) {
+                return Decision::Card {
+                    position: i + 1,
+                    cards: (x, y),
+                    tie_break: self.tie_break,
+                };
+            }
+        }
+        Decision::Tie
+    }
+}
+
+/// Why one hand beat (or lost to) another.
+#[derive(Debug, Clone)]
+enum Decision {
+    Category(Category, Category),
+    /// The first position at which the tie-break order differs; 1-indexed.
+    Card {
+        position: usize,
+        cards: (Card, Card),
+        tie_break: TieBreak,
+    },
+    Tie,
 }
 
-impl Ord for WildCardRules {
-    fn cmp(&self, other: &Self) -> Ordering {
-        // Break ties by lex ordering the card string.
-        let key = |wild: &Self| (wild.category(), wild.0.cards.map(WildCardOrdering));
-        key(self).cmp(&key(other))
+impl fmt::Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Category(a, b) => write!(f, "decided by category: {a} vs {b}"),
+            Self::Card {
+                position,
+                cards: (a, b),
+                tie_break,
+            } => {
+                let order = match tie_break {
+                    TieBreak::InOrder => "",
+                    TieBreak::ByGroup => " after grouping",
+                };
+                write!(
+                    f,
+                    "same category, decided by card {position}{order}: {a} vs {b}"
+                )
+            }
+            Self::Tie => write!(f, "exact tie"),
+        }
     }
 }
 
-impl PartialOrd for WildCardRules {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.straight {
+            return write!(f, "a straight");
+        }
+        let name = match self.groups[..] {
+            [5] => "five of a kind",
+            [4, 1] => "four of a kind",
+            [3, 2] => "a full house",
+            [3, 1, 1] => "three of a kind",
+            [2, 2, 1] => "two pair",
+            [2, 1, 1, 1] => "one pair",
+            [1, 1, 1, 1, 1] => "high card",
+            _ => return write!(f, "groups of {}", self.groups.iter().join("+")),
+        };
+        write!(f, "{name}")
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct WildCardOrdering(Card);
+impl fmt::Display for Hand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for c in &self.cards {
+            write!(f, "{c}")?;
+        }
+        Ok(())
+    }
+}
 
-impl Ord for WildCardOrdering {
-    fn cmp(&self, other: &Self) -> Ordering {
-        // Non-joker cards always rank higher.
-        let key = |wild: &Self| (wild.0 != Card::J, wild.0);
-        key(self).cmp(&key(other))
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Card::*;
+        let c = match self {
+            C2 => '2',
+            C3 => '3',
+            C4 => '4',
+            C5 => '5',
+            C6 => '6',
+            C7 => '7',
+            C8 => '8',
+            C9 => '9',
+            T => 'T',
+            J => 'J',
+            Q => 'Q',
+            K => 'K',
+            A => 'A',
+        };
+        write!(f, "{c}")
     }
 }
 
-impl PartialOrd for WildCardOrdering {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: [&str; 5] = [
+        "32T3K 765",
+        "T55J5 684",
+        "KK677 28",
+        "KTJJT 220",
+        "QQQJA 483",
+    ];
+
+    fn total_winnings(rules: &RuleSet) -> Result<u32> {
+        let mut hands: Vec<_> = SAMPLE.iter().map(|l| parse_line(l, rules)).try_collect()?;
+        hands.sort_by(|(a, _), (b, _)| rules.cmp(a, b));
+        Ok((1..).zip(hands).map(|(i, (_, bid))| i * bid).sum())
+    }
+
+    #[test]
+    fn sample() -> Result<()> {
+        assert_eq!(total_winnings(&RuleSet::standard())?, 6440);
+        assert_eq!(total_winnings(&RuleSet::jokers())?, 5905);
+        Ok(())
+    }
+
+    #[test]
+    fn straights_need_five_cards() -> Result<()> {
+        for n in 1..5 {
+            let text = format!("hand_size: {n}\nstraights: true\n");
+            assert!(RuleSet::parse(&text).is_err(), "{n}-card straights");
+            RuleSet::parse(&format!("hand_size: {n}\n"))?;
+        }
+
+        let rules = RuleSet::parse("hand_size: 6\nstraights: true\n")?;
+        let straight = rules.category(&parse_hand("234567", &rules)?);
+        assert!(straight.straight);
+        let three = rules.category(&parse_hand("222AKQ", &rules)?);
+        let full_house = rules.category(&parse_hand("222AAK", &rules)?);
+        assert!(three < straight && straight < full_house);
+        Ok(())
+    }
+
+    #[test]
+    fn unranked_cards_are_rejected() -> Result<()> {
+        let rules = RuleSet::parse("ranks: 23456789TQKA\n")?;
+        assert!(parse_hand("2345J", &rules).is_err());
+        parse_hand("2345Q", &rules)?;
+        assert!(RuleSet::parse("ranks: 23456789TQKA\nwild: J\n").is_err());
+        Ok(())
     }
 }
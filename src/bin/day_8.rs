@@ -1,6 +1,11 @@
-use std::{collections::HashMap, io};
+use std::{
+    collections::{HashMap, HashSet},
+    env, io,
+};
 
 use anyhow::{bail, ensure, Context, Result};
+use itertools::Itertools;
+use num_bigint::{BigInt, BigUint};
 use once_cell::sync::Lazy;
 use regex::Regex;
 
@@ -12,9 +17,16 @@ fn part_1() -> Result<()> {
     Ok(())
 }
 
+/// Usage: `day_8 [--analyze | --dot]`
 fn main() -> Result<()> {
+    let arg = env::args().nth(1);
     let (directions, graph) = read_input()?;
-    part_2(&directions, &graph)?;
+    match arg.as_deref() {
+        None => part_2(&directions, &graph)?,
+        Some("--analyze") => analyze(&graph),
+        Some("--dot") => print!("{}", to_dot(&directions, &graph)),
+        Some(arg) => bail!("unknown argument: {arg:?}"),
+    }
     Ok(())
 }
 
@@ -44,7 +56,7 @@ fn read_input() -> Result<Input> {
     let mut graph = HashMap::new();
     for l in lines {
         static RE: Lazy<Regex> = Lazy::new(|| {
-            let label = "([[:alpha:]]+)";
+            let label = "([[:alnum:]]+)";
             let re = format!(r"^{label} = \({label}, {label}\)$");
             Regex::new(&re).unwrap()
         });
@@ -55,9 +67,19 @@ fn read_input() -> Result<Input> {
         let left = caps[2].to_owned();
         let right = caps[3].to_owned();
 
+        ensure!(!graph.contains_key(&node), "duplicate node: {node:?}");
         graph.insert(node, [left, right]);
     }
 
+    for (node, edges) in &graph {
+        for next in edges {
+            ensure!(
+                graph.contains_key(next),
+                "{node:?} refers to undefined node {next:?}"
+            );
+        }
+    }
+
     Ok((directions, graph))
 }
 
@@ -91,13 +113,19 @@ fn traverse(directions: &[Direction], graph: &Graph) -> usize {
 
 /// Ok, just going based off intuition, I think the naive solution is not going
 /// to work this time.
+///
+/// Gives up after `max_steps`; this is still useful for checking the real
+/// solver on small graphs.
 #[allow(dead_code)]
-fn traverse_part_2(directions: &[Direction], graph: &Graph) -> usize {
+fn traverse_part_2(directions: &[Direction], graph: &Graph, max_steps: usize) -> Option<usize> {
     let mut curr: Vec<_> = graph.keys().filter(|s| s.ends_with('A')).collect();
 
     for (i, &d) in directions.iter().cycle().enumerate() {
         if curr.iter().all(|s| s.ends_with('Z')) {
-            return i;
+            return Some(i);
+        }
+        if i == max_steps {
+            return None;
         }
 
         let edge = match d {
@@ -112,22 +140,93 @@ fn traverse_part_2(directions: &[Direction], graph: &Graph) -> usize {
     unreachable!();
 }
 
-/// Prints out some equations that can be solved by hand.
-///
-/// In particular, we want the LCM (least-common-multiple) of a few different
-/// cycle lengths. You can plug this into wolfram alpha.
 fn part_2(directions: &[Direction], graph: &Graph) -> Result<()> {
-    for start in graph.keys().filter(|s| s.ends_with('A')) {
-        let r = find_repetition(directions, graph, start);
-        dbg!(r);
-    }
+    let ans = solve_part_2(directions, graph).context("the ghosts never all reach Z")?;
+    dbg!(ans);
     Ok(())
 }
 
+/// The first step at which every ghost stands on a Z node, if any.
+fn solve_part_2(directions: &[Direction], graph: &Graph) -> Option<BigUint> {
+    let repetitions: Vec<_> = graph
+        .keys()
+        .filter(|s| s.ends_with('A'))
+        .map(|start| find_repetition(directions, graph, start))
+        .collect();
+    first_common_win(&repetitions)
+}
+
+/// Each ghost's walk is a stem followed by a cycle, and it may win any number
+/// of times in each.
+///
+/// Once every ghost is in its cycle, each one wins exactly on a few residue
+/// classes mod its cycle length; so we combine these using the Chinese
+/// remainder theorem. Before that, we just check each step directly.
+fn first_common_win(repetitions: &[Repetition]) -> Option<BigUint> {
+    let stem_end = repetitions.iter().map(|r| r.stem_length).max().unwrap_or(0);
+    for step in 0..stem_end {
+        if repetitions.iter().all(|r| r.is_winning(step)) {
+            return Some(step.into());
+        }
+    }
+
+    // Each entry (a, m) means "steps congruent to a mod m".
+    let mut classes = vec![(BigInt::ZERO, BigInt::from(1))];
+    for r in repetitions {
+        let m2 = BigInt::from(r.cycle_length);
+        let mut next = vec![];
+        for (a1, m1) in &classes {
+            for res in r.cycle_residues() {
+                next.extend(crt(a1, m1, &BigInt::from(res), &m2));
+            }
+        }
+        next.sort();
+        next.dedup();
+        classes = next;
+    }
+
+    let stem_end = BigInt::from(stem_end);
+    let first = classes
+        .into_iter()
+        .map(|(a, m)| &stem_end + modulo(&(a - &stem_end), &m))
+        .min()?;
+    Some(first.try_into().unwrap())
+}
+
+/// Solve `x = a1 mod m1` and `x = a2 mod m2`, for moduli that needn't be
+/// coprime. Returns `(x, lcm(m1, m2))`, or None if there's no solution.
+fn crt(a1: &BigInt, m1: &BigInt, a2: &BigInt, m2: &BigInt) -> Option<(BigInt, BigInt)> {
+    let (g, p, _) = extended_gcd(m1, m2);
+    let diff = a2 - a1;
+    if modulo(&diff, &g) != BigInt::ZERO {
+        return None;
+    }
+    // m1 * p = g (mod m2), so x = a1 + m1 * p * (diff / g) works.
+    let lcm = m1 / &g * m2;
+    let x = a1 + m1 * p * (diff / &g);
+    Some((modulo(&x, &lcm), lcm))
+}
+
+/// Returns `(g, x, y)` such that `a*x + b*y = g = gcd(a, b)`.
+fn extended_gcd(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
+    let (mut old_r, mut r) = (a.clone(), b.clone());
+    let (mut old_s, mut s) = (BigInt::from(1), BigInt::ZERO);
+    let (mut old_t, mut t) = (BigInt::ZERO, BigInt::from(1));
+    while r != BigInt::ZERO {
+        let q = &old_r / &r;
+        (old_r, r) = (r.clone(), old_r - &q * r);
+        (old_s, s) = (s.clone(), old_s - &q * s);
+        (old_t, t) = (t.clone(), old_t - &q * t);
+    }
+    (old_r, old_s, old_t)
+}
+
+/// The remainder of `a / m`, in the range `0..m`.
+fn modulo(a: &BigInt, m: &BigInt) -> BigInt {
+    ((a % m) + m) % m
+}
+
 #[derive(Debug)]
-// The compiler thinks these fields are never read, but it doesn't realize we're
-// using the Debug impl to print them.
-#[allow(dead_code)]
 struct Repetition {
     stem_length: usize,
     cycle_length: usize,
@@ -170,3 +269,333 @@ fn find_repetition(directions: &[Direction], graph: &Graph, start: &str) -> Repe
 
     unreachable!();
 }
+
+impl Repetition {
+    fn is_winning(&self, step: usize) -> bool {
+        let step = if step < self.stem_length {
+            step
+        } else {
+            self.stem_length + (step - self.stem_length) % self.cycle_length
+        };
+        self.winning_steps.contains(&step)
+    }
+
+    /// The winning steps inside the cycle, mod the cycle length.
+    fn cycle_residues(&self) -> Vec<usize> {
+        self.winning_steps
+            .iter()
+            .filter(|&&s| s >= self.stem_length)
+            .map(|&s| s % self.cycle_length)
+            .collect()
+    }
+}
+
+/// Print a summary of the graph's structure.
+fn analyze(graph: &Graph) {
+    println!("nodes: {}", graph.len());
+
+    let ghost_starts: Vec<_> = graph.keys().filter(|s| s.ends_with('A')).collect();
+    print_unreachable(graph, "the ..A starts (part 2)", &ghost_starts);
+    if let Some((start, _)) = graph.get_key_value("AAA") {
+        print_unreachable(graph, "AAA (part 1)", &[start]);
+    }
+
+    let sccs = strongly_connected_components(graph);
+    println!("strongly connected components: {}", sccs.len());
+    for mut scc in sccs.into_iter().filter(|c| c.len() > 1) {
+        scc.sort();
+        println!("  size {}: {scc:?}", scc.len());
+    }
+}
+
+fn print_unreachable(graph: &Graph, description: &str, starts: &[&String]) {
+    let reachable = reachable_from(graph, starts);
+    let mut unreachable: Vec<_> = graph
+        .keys()
+        .filter(|n| !reachable.contains(n.as_str()))
+        .collect();
+    unreachable.sort();
+    println!(
+        "unreachable from {description}: {} {unreachable:?}",
+        unreachable.len()
+    );
+}
+
+fn reachable_from<'a>(graph: &'a Graph, starts: &[&'a String]) -> HashSet<&'a str> {
+    let mut seen = HashSet::new();
+    let mut stack: Vec<&str> = starts.iter().map(|s| s.as_str()).collect();
+    while let Some(node) = stack.pop() {
+        if seen.insert(node) {
+            stack.extend(graph[node].iter().map(String::as_str));
+        }
+    }
+    seen
+}
+
+/// Tarjan's algorithm.
+///
+/// This is iterative, with an explicit call stack, so that long chains of
+/// nodes can't overflow the real one.
+fn strongly_connected_components(graph: &Graph) -> Vec<Vec<&str>> {
+    let mut next_index = 0;
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    let mut low_link: HashMap<&str, usize> = HashMap::new();
+    let mut stack: Vec<&str> = vec![];
+    let mut on_stack: HashSet<&str> = HashSet::new();
+    let mut sccs = vec![];
+
+    let mut roots: Vec<_> = graph.keys().map(String::as_str).collect();
+    roots.sort();
+    for root in roots {
+        if index.contains_key(root) {
+            continue;
+        }
+
+        // Each frame is a node being visited, and the next of its edges to
+        // follow.
+        let mut calls = vec![(root, 0)];
+        while let Some(&mut (node, ref mut edge)) = calls.last_mut() {
+            if *edge == 0 {
+                index.insert(node, next_index);
+                low_link.insert(node, next_index);
+                next_index += 1;
+                stack.push(node);
+                on_stack.insert(node);
+            }
+
+            if let Some(next) = graph[node].get(*edge) {
+                let next = next.as_str();
+                *edge += 1;
+                if !index.contains_key(next) {
+                    calls.push((next, 0));
+                } else if on_stack.contains(next) {
+                    let low = low_link[node].min(index[next]);
+                    low_link.insert(node, low);
+                }
+                continue;
+            }
+
+            // All the edges are done, so return to the caller.
+            calls.pop();
+            if let Some(&(parent, _)) = calls.last() {
+                let low = low_link[parent].min(low_link[node]);
+                low_link.insert(parent, low);
+            }
+
+            if low_link[node] == index[node] {
+                let mut scc = vec![];
+                loop {
+                    let n = stack.pop().unwrap();
+                    on_stack.remove(n);
+                    scc.push(n);
+                    if n == node {
+                        break;
+                    }
+                }
+                sccs.push(scc);
+            }
+        }
+    }
+    sccs
+}
+
+/// Render the graph in Graphviz DOT format.
+///
+/// Each ghost's cycle (of `(node, direction index)` states, as found by
+/// `find_repetition`) is drawn in its own colour, with the edges labelled by
+/// the direction indices at which the cycle takes them.
+fn to_dot(directions: &[Direction], graph: &Graph) -> String {
+    const COLORS: [&str; 6] = ["red", "blue", "darkgreen", "orange", "purple", "brown"];
+
+    let mut starts: Vec<_> = graph.keys().filter(|s| s.ends_with('A')).collect();
+    starts.sort();
+
+    let mut out = String::from("digraph {\n");
+    let mut legend = vec![];
+
+    // (node, edge) -> [(color, dir_idx)]
+    let mut annotations: HashMap<(&str, usize), Vec<(&str, usize)>> = HashMap::new();
+    for (i, start) in starts.iter().enumerate() {
+        let color = COLORS[i % COLORS.len()];
+        let r = find_repetition(directions, graph, start);
+        legend.push(format!(
+            "{start}: stem {}, cycle {}, winning steps {:?}",
+            r.stem_length, r.cycle_length, r.winning_steps
+        ));
+
+        let mut node = start.as_str();
+        let states = directions.iter().enumerate().cycle();
+        for (step, (dir_idx, &d)) in states.enumerate().take(r.stem_length + r.cycle_length) {
+            let edge = d.edge_index();
+            if step >= r.stem_length {
+                annotations
+                    .entry((node, edge))
+                    .or_default()
+                    .push((color, dir_idx));
+            }
+            node = &graph[node][edge];
+        }
+        out += &format!("  \"{start}\" [color={color}, style=bold];\n");
+    }
+
+    let mut nodes: Vec<_> = graph.keys().collect();
+    nodes.sort();
+    for node in nodes {
+        for (edge, next) in graph[node].iter().enumerate() {
+            let dir = ["L", "R"][edge];
+            match annotations.get(&(node.as_str(), edge)) {
+                None => out += &format!("  \"{node}\" -> \"{next}\" [label=\"{dir}\"];\n"),
+                Some(uses) => {
+                    for (color, group) in &uses.iter().group_by(|(color, _)| *color) {
+                        let idxs = group.map(|(_, i)| i).join(",");
+                        out += &format!(
+                            "  \"{node}\" -> \"{next}\" [label=\"{dir} @ {idxs}\", color={color}];\n"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    out += &format!("  label=\"{}\";\n", legend.join("\\l"));
+    out += "}\n";
+    out
+}
+
+impl Direction {
+    fn edge_index(self) -> usize {
+        match self {
+            Direction::Left => 0,
+            Direction::Right => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn sample() {
+        let graph: Graph = [
+            ("11A", ["11B", "XXX"]),
+            ("11B", ["XXX", "11Z"]),
+            ("11Z", ["11B", "XXX"]),
+            ("22A", ["22B", "XXX"]),
+            ("22B", ["22C", "22C"]),
+            ("22C", ["22Z", "22Z"]),
+            ("22Z", ["22B", "22B"]),
+            ("XXX", ["XXX", "XXX"]),
+        ]
+        .into_iter()
+        .map(|(node, [l, r])| (node.to_string(), [l.to_string(), r.to_string()]))
+        .collect();
+        let directions = [Direction::Left, Direction::Right];
+        assert_eq!(solve_part_2(&directions, &graph), Some(6u32.into()));
+    }
+
+    #[test]
+    fn crt_with_common_factors() {
+        let n = |x: i32| BigInt::from(x);
+        assert_eq!(crt(&n(2), &n(4), &n(4), &n(6)), Some((n(10), n(12))));
+        assert_eq!(crt(&n(1), &n(4), &n(2), &n(6)), None);
+    }
+
+    /// Check the solver against `traverse_part_2`, on small random graphs.
+    #[test]
+    fn agrees_with_naive_traversal() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..1000 {
+            let (directions, graph) = random_input(&mut rng);
+            let expected = {
+                let reps: Vec<_> = graph
+                    .keys()
+                    .filter(|s| s.ends_with('A'))
+                    .map(|start| find_repetition(&directions, &graph, start))
+                    .collect();
+                // If the ghosts ever line up, they do so before this.
+                let bound = reps.iter().map(|r| r.stem_length).max().unwrap_or(0)
+                    + reps.iter().map(|r| r.cycle_length).fold(1, lcm);
+                traverse_part_2(&directions, &graph, bound)
+            };
+            let actual = solve_part_2(&directions, &graph);
+            assert_eq!(
+                actual,
+                expected.map(BigUint::from),
+                "{directions:?}\n{graph:?}"
+            );
+        }
+    }
+
+    fn lcm(a: usize, b: usize) -> usize {
+        let (mut x, mut y) = (a, b);
+        while y != 0 {
+            (x, y) = (y, x % y);
+        }
+        a / x * b
+    }
+
+    fn random_input(rng: &mut impl Rng) -> Input {
+        let directions = (0..rng.gen_range(1..=4))
+            .map(|_| {
+                if rng.gen() {
+                    Direction::Left
+                } else {
+                    Direction::Right
+                }
+            })
+            .collect();
+
+        let n = rng.gen_range(1..=8);
+        let names: Vec<_> = (0..n)
+            .map(|i| {
+                let suffix = *['A', 'Z', 'X'].choose(rng).unwrap();
+                format!("{}{suffix}", (b'B' + i) as char)
+            })
+            .collect();
+        let graph = names
+            .iter()
+            .map(|name| {
+                let left = names.choose(rng).unwrap().clone();
+                let right = names.choose(rng).unwrap().clone();
+                (name.clone(), [left, right])
+            })
+            .collect();
+
+        (directions, graph)
+    }
+
+    #[test]
+    fn sccs_of_a_long_chain() {
+        // A chain this long would overflow the stack, if we recursed.
+        let n = 200_000;
+        let name = |i: usize| format!("N{i}");
+        let graph: Graph = (0..n)
+            .map(|i| (name(i), [name((i + 1) % n), name(i.saturating_sub(1))]))
+            .collect();
+        let sccs = strongly_connected_components(&graph);
+        assert_eq!(sccs.len(), 1);
+        assert_eq!(sccs[0].len(), n);
+    }
+
+    #[test]
+    fn sccs_of_a_small_graph() {
+        let graph: Graph = [
+            ("AA", ["BB", "BB"]),
+            ("BB", ["CC", "AA"]),
+            ("CC", ["DD", "DD"]),
+            ("DD", ["DD", "DD"]),
+        ]
+        .into_iter()
+        .map(|(node, [l, r])| (node.to_string(), [l.to_string(), r.to_string()]))
+        .collect();
+        let mut sccs = strongly_connected_components(&graph);
+        for scc in &mut sccs {
+            scc.sort();
+        }
+        sccs.sort();
+        assert_eq!(sccs, [vec!["AA", "BB"], vec!["CC"], vec!["DD"]]);
+    }
+}
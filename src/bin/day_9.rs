@@ -1,39 +1,22 @@
-use std::io;
+use std::env;
 
-use anyhow::Result;
-use itertools::Itertools;
+use anyhow::{bail, Result};
 
+/// Usage: `day_9 [STEPS] [--polynomials]`
+///
+/// STEPS defaults to -1, i.e. part 2.
 fn main() -> Result<()> {
-    let lines = read_input()?;
-
-    let mut sum = 0;
-    for mut l in lines {
-        // sum += extrapolate(&l);
-
-        l.reverse();
-        sum += extrapolate(&l);
-    }
-    dbg!(sum);
-
-    Ok(())
-}
-
-fn read_input() -> Result<Vec<Vec<i32>>> {
-    io::stdin().lines().map(parse_line).collect()
-}
-
-fn parse_line(line: io::Result<String>) -> Result<Vec<i32>> {
-    Ok(line?.split_whitespace().map(str::parse).try_collect()?)
-}
-
-fn extrapolate(line: &[i32]) -> i32 {
-    assert!(!line.is_empty());
-
-    if line.iter().all(|&x| x == 0) {
-        return 0;
+    let mut steps = -1;
+    let mut show_polynomials = false;
+    for arg in env::args().skip(1) {
+        if arg == "--polynomials" {
+            show_polynomials = true;
+        } else if let Ok(n) = arg.parse() {
+            steps = n;
+        } else {
+            bail!("unknown argument: {arg:?}");
+        }
     }
 
-    let diffs: Vec<_> = line.windows(2).map(|pair| pair[1] - pair[0]).collect();
-    let new_diff = extrapolate(&diffs);
-    line.last().unwrap() + new_diff
+    advent_2023::day_9::solve(steps, show_polynomials)
 }
@@ -0,0 +1,292 @@
+mod arrangements;
+mod line;
+mod nonogram;
+
+use std::io;
+
+use anyhow::{ensure, Context, Result};
+use itertools::Itertools;
+use num_bigint::BigUint;
+use rand::prelude::*;
+
+use self::{
+    arrangements::Arrangement,
+    line::{Condition, Line},
+    nonogram::{Outcome, Puzzle},
+};
+
+/// How to unfold each line: `count` copies, joined by `separator`s.
+///
+/// Part 1 is one copy, and part 2 is five copies joined by `?`s.
+#[derive(Debug, Clone, Copy)]
+pub struct Unfold {
+    count: usize,
+    separator: Condition,
+}
+
+impl Unfold {
+    pub fn new(count: usize, separator: char) -> Result<Self> {
+        ensure!(count != 0, "need at least one copy");
+        Ok(Self {
+            count,
+            separator: Condition::new(separator).context("separator")?,
+        })
+    }
+}
+
+/// Sum the number of arrangements of each line, for every unfolding from 1 up
+/// to `unfold.count` copies.
+pub fn solve(unfold: Unfold) -> Result<()> {
+    let mut sums = vec![BigUint::ZERO; unfold.count];
+    for l in line::read_input()? {
+        let counts = l.solve_unfoldings(unfold.count, unfold.separator);
+        for (sum, n) in sums.iter_mut().zip(counts) {
+            *sum += n;
+        }
+    }
+    for (k, sum) in sums.iter().enumerate() {
+        println!("{}: {sum}", k + 1);
+    }
+
+    let sum = &sums[unfold.count - 1];
+    dbg!(sum);
+    Ok(())
+}
+
+/// For each line, print the number of arrangements, along with the first,
+/// last, and a random one.
+pub fn examples(unfold: Unfold) -> Result<()> {
+    let mut rng = thread_rng();
+    for l in read_lines(unfold)? {
+        let arrs = l.arrangements();
+        println!("{l}");
+        println!("  {} arrangements", arrs.len());
+        if let Some(first) = arrs.nth(&BigUint::ZERO) {
+            println!("  first:  {first}");
+            println!("  last:   {}", arrs.nth(&(arrs.len() - 1u32)).unwrap());
+            println!("  random: {}", arrs.sample(&mut rng).unwrap());
+        }
+    }
+    Ok(())
+}
+
+/// Print every arrangement of the `row`-th line (counting from 1), in
+/// lexicographic order.
+pub fn list(unfold: Unfold, row: usize) -> Result<()> {
+    let l = nth_line(unfold, row)?;
+    for a in l.arrangements().iter() {
+        println!("{a}");
+    }
+    Ok(())
+}
+
+/// Print the `k`-th arrangement of the `row`-th line (both counting from 1).
+pub fn nth(unfold: Unfold, row: usize, k: &BigUint) -> Result<()> {
+    let l = nth_line(unfold, row)?;
+    let arrs = l.arrangements();
+    ensure!(
+        *k != BigUint::ZERO && k <= arrs.len(),
+        "line {row} has {} arrangements",
+        arrs.len()
+    );
+    println!("{}", arrs.nth(&(k - 1u32)).unwrap());
+    Ok(())
+}
+
+/// Print where `arrangement` comes in the `row`-th line's list of
+/// arrangements (both counting from 1). The inverse of `nth`.
+pub fn rank(unfold: Unfold, row: usize, arrangement: &str) -> Result<()> {
+    let l = nth_line(unfold, row)?;
+    let conditions = arrangement.chars().map(Condition::new).try_collect()?;
+    let k = l
+        .arrangements()
+        .rank(&Arrangement(conditions))
+        .with_context(|| format!("{arrangement} isn't an arrangement of line {row}"))?;
+    println!("{}", k + 1u32);
+    Ok(())
+}
+
+/// Solve a 2D nonogram, read from stdin. See `Puzzle` for the format.
+pub fn nonogram() -> Result<()> {
+    let puzzle = Puzzle::parse(&io::read_to_string(io::stdin())?)?;
+    let report = puzzle.solve();
+    match report.outcome {
+        Outcome::NoSolution => println!("no solution"),
+        Outcome::Unique(grid) => println!("unique solution:\n{grid}"),
+        Outcome::Multiple(a, b) => println!("multiple solutions, e.g.:\n{a}\n{b}"),
+    }
+    dbg!(report.num_guesses);
+    Ok(())
+}
+
+fn read_lines(unfold: Unfold) -> Result<Vec<Line>> {
+    let lines = line::read_input()?;
+    Ok(lines
+        .iter()
+        .map(|l| l.unfold(unfold.count, unfold.separator))
+        .collect())
+}
+
+fn nth_line(unfold: Unfold, row: usize) -> Result<Line> {
+    let lines = read_lines(unfold)?;
+    let num_lines = lines.len();
+    lines
+        .into_iter()
+        .nth(row.wrapping_sub(1))
+        .with_context(|| format!("no line {row}; expected 1..={num_lines}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{ensure, Context, Result};
+    use num_bigint::BigUint;
+    use rand::prelude::*;
+
+    use super::{
+        arrangements::random_below,
+        line::{self, Condition, Line},
+        Unfold,
+    };
+
+    const SAMPLE: &str = "\
+???.### 1,1,3
+.??..??...?##. 1,1,3
+?#?#?#?#?#?#?#? 1,3,1,6
+????.#...#... 4,1,1
+????.######..#####. 1,6,5
+?###???????? 3,2,1
+";
+
+    #[test]
+    fn sample() -> Result<()> {
+        let mut sums = vec![BigUint::ZERO; 5];
+        for l in line::parse(SAMPLE)? {
+            for (sum, n) in sums
+                .iter_mut()
+                .zip(l.solve_unfoldings(5, Condition::Unknown))
+            {
+                *sum += n;
+            }
+        }
+        assert_eq!(sums[0], 21u32.into());
+        assert_eq!(sums[4], 525152u32.into());
+        Ok(())
+    }
+
+    #[test]
+    fn sample_arrangements() -> Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        for unfold in [
+            Unfold::new(1, '?')?,
+            Unfold::new(5, '?')?,
+            Unfold::new(2, '.')?,
+        ] {
+            for l in line::parse(SAMPLE)? {
+                check_arrangements(&l, unfold, &mut rng)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Short random lines, which are cheap to brute force.
+    #[test]
+    fn random_arrangements() -> Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..500 {
+            let l = random_line(&mut rng);
+            check_arrangements(&l, Unfold::new(1, '?')?, &mut rng)?;
+            let unfold = Unfold::new(rng.gen_range(1..=3), ['?', '.', '#'][rng.gen_range(0..3)])?;
+            check_arrangements(&l, unfold, &mut rng)?;
+        }
+        Ok(())
+    }
+
+    /// Check the arrangement API against the counting DP (and against brute
+    /// force, for a single copy).
+    fn check_arrangements(original: &Line, unfold: Unfold, rng: &mut impl Rng) -> Result<()> {
+        let l = original.unfold(unfold.count, unfold.separator);
+        let arrs = l.arrangements();
+        let n = arrs.len();
+        ensure!(*n == l.clone().solve(), "{l}: count mismatch");
+        let incremental = original.solve_unfoldings(unfold.count, unfold.separator);
+        ensure!(
+            incremental.last() == Some(n),
+            "{l}: incremental count mismatch"
+        );
+        if unfold.count == 1 {
+            let brute_force = l.clone().brute_force(0);
+            ensure!(*n == brute_force.into(), "{l}: brute force mismatch");
+        }
+
+        let check = |k: &BigUint| -> Result<String> {
+            let a = arrs.nth(k).context("nth")?;
+            ensure!(l.accepts(&a.0), "{l}: invalid arrangement {a}");
+            ensure!(arrs.rank(&a).as_ref() == Some(k), "{l}: rank({a}) != {k}");
+            Ok(a.to_string())
+        };
+
+        if *n <= BigUint::from(1000u32) {
+            // Every arrangement, in strictly increasing order.
+            let mut all = vec![];
+            let mut k = BigUint::ZERO;
+            while k < *n {
+                all.push(check(&k)?);
+                k += 1u32;
+            }
+            ensure!(all.windows(2).all(|w| w[0] < w[1]), "{l}: out of order");
+            ensure!(arrs.iter().count() == all.len(), "{l}: iter count");
+        } else {
+            for _ in 0..100 {
+                let k = random_below(rng, &(n - 1u32));
+                ensure!(
+                    check(&k)? < check(&(&k + 1u32))?,
+                    "{l}: out of order at {k}"
+                );
+            }
+        }
+
+        if !arrs.is_empty() {
+            for _ in 0..10 {
+                let a = arrs.sample(rng).context("sample")?;
+                ensure!(arrs.rank(&a).is_some(), "{l}: bad sample {a}");
+            }
+        }
+        ensure!(arrs.nth(n).is_none(), "{l}: nth past the end");
+        Ok(())
+    }
+
+    /// Up to 12 conditions, with the group lengths taken from some filling-in
+    /// of the unknowns; so there's usually at least one arrangement.
+    fn random_line(rng: &mut impl Rng) -> Line {
+        loop {
+            let len = rng.gen_range(1..=12);
+            let filled: Vec<_> = (0..len)
+                .map(|_| {
+                    if rng.gen() {
+                        Condition::Damaged
+                    } else {
+                        Condition::Operational
+                    }
+                })
+                .collect();
+            let group_lengths = line::group_lengths(&filled).unwrap();
+            if group_lengths.is_empty() {
+                continue;
+            }
+            let conditions = filled
+                .into_iter()
+                .map(|c| {
+                    if rng.gen_bool(0.5) {
+                        Condition::Unknown
+                    } else {
+                        c
+                    }
+                })
+                .collect();
+            return Line {
+                conditions,
+                group_lengths,
+            };
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::fmt;
+
+use num_bigint::BigUint;
+use rand::prelude::*;
+
+use super::line::{Condition, Line};
+
+/// A concrete way of filling in a line's unknowns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Arrangement(pub Vec<Condition>);
+
+impl fmt::Display for Arrangement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for c in &self.0 {
+            write!(f, "{}", c.to_char())?;
+        }
+        Ok(())
+    }
+}
+
+/// All the arrangements of a line, in lexicographic order.
+///
+/// The order is the one you'd get by sorting the arrangements as strings; so
+/// `#` comes before `.`, since it does in ASCII.
+///
+/// Nothing is enumerated up front. Instead, we keep a table of how many ways
+/// there are to finish each partial arrangement, which lets us jump straight
+/// to the k-th one (and back again).
+#[derive(Debug, Clone)]
+pub struct Arrangements<'a> {
+    line: &'a Line,
+    /// `ways[i][c]` is the number of ways to fit the islands `i..` into the
+    /// conditions `c..`, given that an island may start at `c`.
+    ways: Vec<Vec<BigUint>>,
+}
+
+impl Line {
+    pub fn arrangements(&self) -> Arrangements<'_> {
+        let num_islands = self.group_lengths.len();
+        let num_conds = self.conditions.len();
+        let mut ways = vec![vec![BigUint::ZERO; num_conds + 1]; num_islands + 1];
+        ways[num_islands][num_conds] = BigUint::from(1u32);
+
+        let mut this = Arrangements { line: self, ways };
+        for i in (0..=num_islands).rev() {
+            for c in (0..num_conds).rev() {
+                // Put an island here, or a '.'.
+                let island = this.island_ways(i, c);
+                let gap = if self.conditions[c] == '.' {
+                    this.ways[i][c + 1].clone()
+                } else {
+                    BigUint::ZERO
+                };
+                this.ways[i][c] = island + gap;
+            }
+        }
+        this
+    }
+}
+
+impl Arrangements<'_> {
+    pub fn len(&self) -> &BigUint {
+        &self.ways[0][0]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self.len() == BigUint::ZERO
+    }
+
+    /// The `k`-th arrangement, counting from 0.
+    pub fn nth(&self, k: &BigUint) -> Option<Arrangement> {
+        if k >= self.len() {
+            return None;
+        }
+        let mut k = k.clone();
+
+        let num_conds = self.line.conditions.len();
+        let mut out = Vec::with_capacity(num_conds);
+        let (mut i, mut c) = (0, 0);
+        while c < num_conds {
+            let island = self.island_ways(i, c);
+            if k < island {
+                let len = self.line.group_lengths[i];
+                out.extend([Condition::Damaged].repeat(len));
+                c += len;
+                if c < num_conds {
+                    out.push(Condition::Operational);
+                    c += 1;
+                }
+                i += 1;
+            } else {
+                k -= island;
+                out.push(Condition::Operational);
+                c += 1;
+            }
+        }
+
+        Some(Arrangement(out))
+    }
+
+    /// The inverse of `nth`. Returns `None` if it isn't one of this line's
+    /// arrangements.
+    pub fn rank(&self, arrangement: &Arrangement) -> Option<BigUint> {
+        if !self.line.accepts(&arrangement.0) {
+            return None;
+        }
+
+        let mut k = BigUint::ZERO;
+        let (mut i, mut c) = (0, 0);
+        while c < arrangement.0.len() {
+            match arrangement.0[c] {
+                // Since the arrangement is valid, this island is exactly the
+                // right length.
+                Condition::Damaged => {
+                    c = self.island_at(i, c)?;
+                    i += 1;
+                }
+                // Skip over the arrangements that put an island here.
+                _ => {
+                    k += self.island_ways(i, c);
+                    c += 1;
+                }
+            }
+        }
+
+        Some(k)
+    }
+
+    /// An arrangement chosen uniformly at random.
+    pub fn sample(&self, rng: &mut impl Rng) -> Option<Arrangement> {
+        if self.is_empty() {
+            return None;
+        }
+        self.nth(&random_below(rng, self.len()))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Arrangement> + '_ {
+        let mut k = BigUint::ZERO;
+        std::iter::from_fn(move || {
+            let a = self.nth(&k)?;
+            k += 1u32;
+            Some(a)
+        })
+    }
+
+    /// If island `i` can start at `c`, returns where the next island may
+    /// start.
+    fn island_at(&self, i: usize, c: usize) -> Option<usize> {
+        let conds = &self.line.conditions;
+        let len = *self.line.group_lengths.get(i)?;
+        let end = c + len;
+        if end > conds.len() || !conds[c..end].iter().all(|&x| x == '#') {
+            return None;
+        }
+        if end == conds.len() {
+            Some(end)
+        } else if conds[end] == '.' {
+            Some(end + 1)
+        } else {
+            None
+        }
+    }
+
+    /// The number of arrangements that start island `i` at `c`.
+    fn island_ways(&self, i: usize, c: usize) -> BigUint {
+        self.island_at(i, c)
+            .map_or(BigUint::ZERO, |next| self.ways[i + 1][next].clone())
+    }
+}
+
+/// A uniformly random number in `0..n`, by rejection sampling. Each attempt
+/// succeeds with probability at least 1/2.
+pub fn random_below(rng: &mut impl Rng, n: &BigUint) -> BigUint {
+    assert!(*n != BigUint::ZERO);
+    let bits = n.bits();
+    let num_bytes = bits.div_ceil(8) as usize;
+    loop {
+        let mut bytes = vec![0; num_bytes];
+        rng.fill_bytes(&mut bytes);
+        let mut x = BigUint::from_bytes_le(&bytes);
+        // Throw away the bits above the top bit of `n`.
+        x &= (BigUint::from(1u32) << bits) - 1u32;
+        if x < *n {
+            return x;
+        }
+    }
+}
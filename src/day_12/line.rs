@@ -0,0 +1,254 @@
+use std::{fmt, io};
+
+use anyhow::{bail, ensure, Context, Result};
+use itertools::Itertools;
+use num_bigint::BigUint;
+
+impl Line {
+    pub fn solve(&mut self) -> BigUint {
+        let num_islands = self.group_lengths.len();
+        let num_conds = self.conditions.len();
+        let mut ans = self.count_table();
+
+        // Plus one for the padding.
+        ans[num_islands].swap_remove(num_conds + 1)
+    }
+
+    /// The number of arrangements after unfolding 1, 2, ..., `max` times.
+    ///
+    /// Each unfolding is a prefix of the next one (in both conditions and group
+    /// lengths), so a single table covers all of them.
+    pub fn solve_unfoldings(&self, max: usize, separator: Condition) -> Vec<BigUint> {
+        if max == 0 {
+            return vec![];
+        }
+        let mut ans = self.unfold(max, separator).count_table();
+
+        let num_islands = self.group_lengths.len();
+        let num_conds = self.conditions.len();
+        (1..=max)
+            .map(|k| {
+                let i = k * num_islands;
+                // The separators, plus one for the padding.
+                let c = k * num_conds + (k - 1) + 1;
+                std::mem::take(&mut ans[i][c])
+            })
+            .collect()
+    }
+
+    /// `ans[i][c]` is the number of ways to fit the first `i` islands into the
+    /// first `c` conditions, after left-padding with a '.'.
+    fn count_table(&mut self) -> Vec<Vec<BigUint>> {
+        // Left-pad with '.' to make bounds-checking / edge-cases easier.
+        self.conditions.insert(0, Condition::Operational);
+
+        let num_islands = self.group_lengths.len();
+        let num_conds = self.conditions.len();
+        let mut ans = vec![vec![BigUint::ZERO; num_conds + 1]; num_islands + 1];
+
+        for i in 0..=num_islands {
+            for c in 0..=num_conds {
+                // Base case: no islands and empty input string.
+                if (i, c) == (0, 0) {
+                    ans[0][0] = BigUint::from(1u32);
+                    continue;
+                }
+
+                let mut ways = BigUint::ZERO;
+
+                // The two recursive cases are "use it, or don't".
+
+                // Use it.
+                if i != 0 {
+                    // Make sure the pattern ".###" is compatible.
+                    // (Using a number of #s equal to the current island length.)
+                    let pat_len = 1 + self.group_lengths[i - 1];
+                    if c >= pat_len
+                        && self.conditions[c - pat_len] == '.'
+                        && (c - pat_len + 1..=c - 1).all(|c2| self.conditions[c2] == '#')
+                    {
+                        ways += &ans[i - 1][c - pat_len];
+                    }
+                }
+
+                // Don't (at least not yet).
+                if c != 0 && self.conditions[c - 1] == '.' {
+                    ways += &ans[i][c - 1];
+                }
+
+                ans[i][c] = ways;
+            }
+        }
+
+        // Restore original state.
+        self.conditions.remove(0);
+
+        ans
+    }
+
+    /// `count` copies of the conditions, joined by `separator`s, and `count`
+    /// copies of the group lengths. Part 2 is five copies, joined by `?`s.
+    pub fn unfold(&self, count: usize, separator: Condition) -> Self {
+        let mut conditions = self.conditions.clone();
+        conditions.push(separator);
+        conditions = conditions.repeat(count);
+        conditions.pop();
+
+        Self {
+            conditions,
+            group_lengths: self.group_lengths.repeat(count),
+        }
+    }
+
+    /// Is `arrangement` a way of filling in this line's unknowns?
+    pub fn accepts(&self, arrangement: &[Condition]) -> bool {
+        arrangement.len() == self.conditions.len()
+            && !arrangement.contains(&Condition::Unknown)
+            && self
+                .conditions
+                .iter()
+                .zip(arrangement)
+                .all(|(&c, &a)| c == a.to_char())
+            && group_lengths(arrangement).as_ref() == Some(&self.group_lengths)
+    }
+}
+
+impl Condition {
+    pub fn to_char(self) -> char {
+        match self {
+            Condition::Operational => '.',
+            Condition::Damaged => '#',
+            Condition::Unknown => '?',
+        }
+    }
+}
+
+impl PartialEq<char> for Condition {
+    fn eq(&self, symbol: &char) -> bool {
+        let this = self.to_char();
+
+        // Treat ?s as wildcards.
+        this == '?' || *symbol == '?' || this == *symbol
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub conditions: Vec<Condition>,
+    pub group_lengths: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    Operational,
+    Damaged,
+    Unknown,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for c in &self.conditions {
+            write!(f, "{}", c.to_char())?;
+        }
+        write!(f, " {}", self.group_lengths.iter().join(","))
+    }
+}
+
+pub fn read_input() -> Result<Vec<Line>> {
+    parse(&io::read_to_string(io::stdin())?)
+}
+
+pub fn parse(text: &str) -> Result<Vec<Line>> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| parse_line(i + 1, line))
+        .collect()
+}
+
+/// Errors are reported with 1-based line and column numbers.
+fn parse_line(line_num: usize, line: &str) -> Result<Line> {
+    let at = |col: usize| format!("line {line_num}, column {col}");
+
+    let (conditions, group_lengths) = line
+        .split_once(' ')
+        .with_context(|| format!("line {line_num}: expected a space"))?;
+
+    let conditions = conditions
+        .chars()
+        .enumerate()
+        .map(|(i, c)| Condition::new(c).with_context(|| at(i + 1)))
+        .try_collect()?;
+
+    // Skip past the conditions and the space.
+    let mut col = line.len() - group_lengths.len() + 1;
+    let mut lengths = vec![];
+    for n in group_lengths.split(',') {
+        let len: usize = n
+            .parse()
+            .with_context(|| format!("invalid group length: {n:?}"))
+            .with_context(|| at(col))?;
+        ensure!(len != 0, "{}: group length can't be zero", at(col));
+        lengths.push(len);
+        col += n.len() + 1;
+    }
+
+    Ok(Line {
+        conditions,
+        group_lengths: lengths,
+    })
+}
+
+impl Condition {
+    pub fn new(c: char) -> Result<Self> {
+        Ok(match c {
+            '.' => Self::Operational,
+            '#' => Self::Damaged,
+            '?' => Self::Unknown,
+            _ => bail!("invalid condition symbol: {c:?}"),
+        })
+    }
+}
+
+#[cfg(test)]
+impl Line {
+    pub fn brute_force(&mut self, i: usize) -> usize {
+        // Base case: no unknowns.
+        if i == self.conditions.len() {
+            let gl = group_lengths(&self.conditions);
+            return if gl.as_ref() == Some(&self.group_lengths) {
+                1
+            } else {
+                0
+            };
+        }
+
+        // Brute force, try both possibilities.
+        if self.conditions[i] == Condition::Unknown {
+            let mut ans = 0;
+            self.conditions[i] = Condition::Operational;
+            ans += self.brute_force(i + 1);
+            self.conditions[i] = Condition::Damaged;
+            ans += self.brute_force(i + 1);
+            self.conditions[i] = Condition::Unknown; // restore original state
+            return ans;
+        }
+
+        // Happy path; keep scanning for unknowns.
+        self.brute_force(i + 1)
+    }
+}
+
+/// Returns `None` if there are any unknowns.
+pub fn group_lengths(conditions: &[Condition]) -> Option<Vec<usize>> {
+    if conditions.contains(&Condition::Unknown) {
+        return None;
+    }
+    let lengths = conditions
+        .iter()
+        .group_by(|&&c| c)
+        .into_iter()
+        .filter(|(c, _)| *c == Condition::Damaged)
+        .map(|(_, g)| g.count())
+        .collect();
+    Some(lengths)
+}
@@ -0,0 +1,289 @@
+use std::fmt;
+
+use anyhow::{bail, ensure, Context, Result};
+use itertools::Itertools;
+use num_bigint::BigUint;
+
+use super::line::{group_lengths, Condition, Line};
+
+/// A 2D nonogram: a grid to fill in, with the group lengths given for every
+/// row and column.
+///
+/// The puzzle format is a `rows` section followed by a `cols` section, with
+/// one clue per line, e.g.:
+///
+/// ```text
+/// rows
+/// 1,1
+/// 3
+/// 0
+/// cols
+/// 2
+/// 1
+/// 2
+/// ```
+///
+/// A clue of `0` means the line is empty. Blank lines are ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Puzzle {
+    rows: Vec<Vec<usize>>,
+    cols: Vec<Vec<usize>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid {
+    cells: Vec<Vec<Condition>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    NoSolution,
+    Unique(Grid),
+    /// Two of the solutions.
+    Multiple(Grid, Grid),
+}
+
+/// The result of solving, along with how many guesses it took.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub outcome: Outcome,
+    pub num_guesses: usize,
+}
+
+/// Two lines which can't both be satisfied.
+struct Contradiction;
+
+impl Puzzle {
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut rows = vec![];
+        let mut cols = vec![];
+        let mut section = None;
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            match line.trim_end_matches(':') {
+                "" => continue,
+                "rows" => section = Some(&mut rows),
+                "cols" => section = Some(&mut cols),
+                _ => {
+                    let clues = section
+                        .as_mut()
+                        .with_context(|| format!("line {}: clue before header", i + 1))?;
+                    clues.push(parse_clue(line).with_context(|| format!("line {}", i + 1))?);
+                }
+            }
+        }
+        ensure!(!rows.is_empty(), "no rows");
+        ensure!(!cols.is_empty(), "no cols");
+        Ok(Self { rows, cols })
+    }
+
+    /// Deduce as much as possible line by line, then guess; repeat.
+    ///
+    /// Stops as soon as a second solution turns up.
+    pub fn solve(&self) -> Report {
+        let grid = Grid {
+            cells: vec![vec![Condition::Unknown; self.cols.len()]; self.rows.len()],
+        };
+        let mut solutions = vec![];
+        let mut num_guesses = 0;
+        self.search(grid, &mut solutions, &mut num_guesses);
+
+        let outcome = match solutions.len() {
+            0 => Outcome::NoSolution,
+            1 => Outcome::Unique(solutions.pop().unwrap()),
+            _ => {
+                let b = solutions.pop().unwrap();
+                let a = solutions.pop().unwrap();
+                Outcome::Multiple(a, b)
+            }
+        };
+        Report {
+            outcome,
+            num_guesses,
+        }
+    }
+
+    fn search(&self, mut grid: Grid, solutions: &mut Vec<Grid>, num_guesses: &mut usize) {
+        if self.propagate(&mut grid).is_err() {
+            return;
+        }
+
+        let Some((r, c)) = grid.first_unknown() else {
+            assert!(self.is_solved_by(&grid), "bad solution:\n{grid}");
+            solutions.push(grid);
+            return;
+        };
+
+        *num_guesses += 1;
+        for guess in [Condition::Damaged, Condition::Operational] {
+            if solutions.len() >= 2 {
+                return;
+            }
+            let mut g = grid.clone();
+            g.cells[r][c] = guess;
+            self.search(g, solutions, num_guesses);
+        }
+    }
+
+    /// Solve each row and column in turn, until nothing changes.
+    fn propagate(&self, grid: &mut Grid) -> Result<(), Contradiction> {
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for (r, clue) in self.rows.iter().enumerate() {
+                let cells = grid.cells[r].clone();
+                for (c, cond) in solve_line(cells, clue)?.into_iter().enumerate() {
+                    if grid.cells[r][c] != cond {
+                        grid.cells[r][c] = cond;
+                        changed = true;
+                    }
+                }
+            }
+
+            for (c, clue) in self.cols.iter().enumerate() {
+                let cells = grid.cells.iter().map(|row| row[c]).collect();
+                for (r, cond) in solve_line(cells, clue)?.into_iter().enumerate() {
+                    if grid.cells[r][c] != cond {
+                        grid.cells[r][c] = cond;
+                        changed = true;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn is_solved_by(&self, grid: &Grid) -> bool {
+        let rows_ok = (grid.cells.iter())
+            .zip(&self.rows)
+            .all(|(row, clue)| group_lengths(row).as_ref() == Some(clue));
+        let cols_ok = self.cols.iter().enumerate().all(|(c, clue)| {
+            let col: Vec<_> = grid.cells.iter().map(|row| row[c]).collect();
+            group_lengths(&col).as_ref() == Some(clue)
+        });
+        rows_ok && cols_ok
+    }
+}
+
+/// Fill in every cell that's the same in all of the line's arrangements.
+///
+/// For each unknown cell, we count the arrangements where it's damaged. If
+/// there are none, it must be operational; if that's all of them, it must be
+/// damaged.
+fn solve_line(conditions: Vec<Condition>, clue: &[usize]) -> Result<Vec<Condition>, Contradiction> {
+    let mut line = Line {
+        conditions,
+        group_lengths: clue.to_vec(),
+    };
+    let total = line.solve();
+    if total == BigUint::ZERO {
+        return Err(Contradiction);
+    }
+
+    for i in 0..line.conditions.len() {
+        if line.conditions[i] != Condition::Unknown {
+            continue;
+        }
+        line.conditions[i] = Condition::Damaged;
+        let damaged = line.solve();
+        line.conditions[i] = if damaged == BigUint::ZERO {
+            Condition::Operational
+        } else if damaged == total {
+            Condition::Damaged
+        } else {
+            Condition::Unknown
+        };
+    }
+
+    Ok(line.conditions)
+}
+
+fn parse_clue(s: &str) -> Result<Vec<usize>> {
+    if s == "0" {
+        return Ok(vec![]);
+    }
+    let clue: Vec<usize> = s.split(',').map(|n| n.trim().parse()).try_collect()?;
+    if clue.contains(&0) {
+        bail!("zero-length group in {s:?}");
+    }
+    Ok(clue)
+}
+
+impl Grid {
+    fn first_unknown(&self) -> Option<(usize, usize)> {
+        self.cells.iter().enumerate().find_map(|(r, row)| {
+            let c = row.iter().position(|&x| x == Condition::Unknown)?;
+            Some((r, c))
+        })
+    }
+}
+
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in &self.cells {
+            for c in row {
+                write!(f, "{}", c.to_char())?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Solve the puzzle, and check that every grid it returns is a solution.
+    fn solve(puzzle: &str) -> Result<Outcome> {
+        let puzzle = Puzzle::parse(puzzle)?;
+        let outcome = puzzle.solve().outcome;
+        let grids = match &outcome {
+            Outcome::NoSolution => vec![],
+            Outcome::Unique(a) => vec![a],
+            Outcome::Multiple(a, b) => {
+                ensure!(a != b, "the same solution twice:\n{a}");
+                vec![a, b]
+            }
+        };
+        for g in grids {
+            ensure!(puzzle.is_solved_by(g), "not a solution:\n{g}");
+        }
+        Ok(outcome)
+    }
+
+    #[test]
+    fn unique() -> Result<()> {
+        let outcome = solve("rows\n1,1\n3\n0\ncols\n2\n1\n2\n")?;
+        let Outcome::Unique(grid) = outcome else {
+            bail!("expected a unique solution, got {outcome:?}");
+        };
+        ensure!(grid.to_string() == "#.#\n###\n...\n", "grid:\n{grid}");
+        Ok(())
+    }
+
+    /// Either diagonal of a 2x2 grid.
+    #[test]
+    fn multiple() -> Result<()> {
+        let outcome = solve("rows\n1\n1\ncols\n1\n1\n")?;
+        let Outcome::Multiple(a, b) = outcome else {
+            bail!("expected multiple solutions, got {outcome:?}");
+        };
+        let mut grids = [a.to_string(), b.to_string()];
+        grids.sort();
+        ensure!(grids == ["#.\n.#\n", ".#\n#.\n"], "grids: {grids:?}");
+        Ok(())
+    }
+
+    /// The rows need two cells filled in, but the columns allow none.
+    #[test]
+    fn contradiction() -> Result<()> {
+        let outcome = solve("rows\n2\n0\ncols\n0\n0\n")?;
+        ensure!(
+            matches!(outcome, Outcome::NoSolution),
+            "expected no solution, got {outcome:?}"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+mod bitboard;
+mod grid;
+mod program;
+#[cfg(test)]
+mod tests;
+
+use anyhow::{Context, Result};
+
+use self::{
+    bitboard::Bitboard,
+    program::{Direction, Program},
+};
+
+/// Part 2 is a billion spin cycles.
+pub const PART_2: &str = "(N W S E)^1000000000";
+
+/// Run a tilt program (see `Program` for the syntax), and then measure the
+/// load against `edge`.
+pub fn solve(program: &str, edge: char) -> Result<()> {
+    let program = Program::parse(program).context("invalid program")?;
+    let edge = Direction::new(edge).context("invalid edge")?;
+
+    let mut board = Bitboard::new(&grid::read_input()?)?;
+    program.run(&mut board, true);
+    let ans = board.load(edge);
+    dbg!(ans);
+    Ok(())
+}
@@ -0,0 +1,316 @@
+#[cfg(test)]
+use std::collections::HashMap;
+use std::fmt;
+
+use anyhow::{ensure, Result};
+
+#[cfg(test)]
+use super::grid::Repetition;
+use super::{
+    grid::{Input, Tile},
+    program::Direction,
+};
+
+/// The same board as `Input`, but bit-packed: each row is a pair of masks,
+/// with column `j` in bit `j % 128` of the row's word `j / 128`.
+///
+/// Tilting east or west moves every rock in a row one step at a time, with a
+/// shift and a mask; tilting north or south does the same between adjacent
+/// rows. Only the rocks ever move, so they're all we need to hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitboard {
+    ncols: usize,
+    /// Words per row. Row `i` is `rocks[i * width..(i + 1) * width]`.
+    width: usize,
+    rocks: Vec<u128>,
+    obstacles: Vec<u128>,
+}
+
+impl Bitboard {
+    pub fn new(input: &Input) -> Result<Self> {
+        let (_, ncols) = input.dims();
+        let width = ncols.div_ceil(128).max(1);
+
+        let mut rocks = vec![];
+        let mut obstacles = vec![];
+        for row in &input.grid {
+            ensure!(row.len() == ncols, "rows have different lengths");
+            rocks.extend(pack(row, Tile::Rock, width));
+            obstacles.extend(pack(row, Tile::Obstacle, width));
+        }
+
+        Ok(Self {
+            ncols,
+            width,
+            rocks,
+            obstacles,
+        })
+    }
+
+    pub fn to_input(&self) -> Input {
+        let grid = (self.rocks.chunks(self.width))
+            .zip(self.obstacles.chunks(self.width))
+            .map(|(rocks, obstacles)| {
+                (0..self.ncols)
+                    .map(|j| {
+                        if bit(rocks, j) {
+                            Tile::Rock
+                        } else if bit(obstacles, j) {
+                            Tile::Obstacle
+                        } else {
+                            Tile::Empty
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        Input { grid }
+    }
+
+    #[cfg(test)]
+    pub fn simulate_spin_cycles(&mut self, num_cycles: usize) {
+        let r = self.find_repetition();
+
+        let mut num_cycles = num_cycles;
+        if num_cycles > r.stem_len {
+            num_cycles -= r.stem_len;
+            num_cycles %= r.cycle_len;
+            num_cycles += r.stem_len;
+        }
+
+        for _ in 0..num_cycles {
+            self.spin_cycle();
+        }
+    }
+
+    #[cfg(test)]
+    pub fn find_repetition(&self) -> Repetition {
+        let mut state = self.clone();
+
+        let mut seen = HashMap::new();
+        for i in 0.. {
+            if let Some(&stem_len) = seen.get(state.state()) {
+                let cycle_len = i - stem_len;
+                return Repetition {
+                    stem_len,
+                    cycle_len,
+                };
+            }
+            seen.insert(state.state().to_vec(), i);
+
+            state.spin_cycle();
+        }
+
+        unreachable!();
+    }
+
+    /// Everything that changes as the board tilts.
+    pub fn state(&self) -> &[u128] {
+        &self.rocks
+    }
+
+    pub fn tilt(&mut self, dir: Direction) {
+        match dir {
+            Direction::North => self.roll_north(),
+            Direction::East => self.roll_east(),
+            Direction::South => self.roll_south(),
+            Direction::West => self.roll_west(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn spin_cycle(&mut self) {
+        self.roll_north();
+        self.roll_west();
+        self.roll_south();
+        self.roll_east();
+    }
+
+    pub fn roll_north(&mut self) {
+        // Rows above `i` are already settled, so each row's rocks only need to
+        // sink upwards through them.
+        for i in 1..self.nrows() {
+            for j in (1..=i).rev() {
+                if !self.step_between(j, j - 1) {
+                    break;
+                }
+            }
+        }
+    }
+
+    pub fn roll_south(&mut self) {
+        let nrows = self.nrows();
+        for i in (0..nrows.saturating_sub(1)).rev() {
+            for j in i..nrows - 1 {
+                if !self.step_between(j, j + 1) {
+                    break;
+                }
+            }
+        }
+    }
+
+    pub fn roll_west(&mut self) {
+        let full = self.full_masks();
+        for (rocks, obstacles) in
+            (self.rocks.chunks_mut(self.width)).zip(self.obstacles.chunks(self.width))
+        {
+            loop {
+                let empty = empty_cells(rocks, obstacles, &full);
+                // Rocks with an empty cell to their west.
+                let movable = and(rocks, &shift_up(&empty));
+                if movable.iter().all(|&m| m == 0) {
+                    break;
+                }
+                move_rocks(rocks, &movable, &shift_down(&movable));
+            }
+        }
+    }
+
+    pub fn roll_east(&mut self) {
+        let full = self.full_masks();
+        for (rocks, obstacles) in
+            (self.rocks.chunks_mut(self.width)).zip(self.obstacles.chunks(self.width))
+        {
+            loop {
+                let empty = empty_cells(rocks, obstacles, &full);
+                // Rocks with an empty cell to their east.
+                let movable = and(rocks, &shift_down(&empty));
+                if movable.iter().all(|&m| m == 0) {
+                    break;
+                }
+                move_rocks(rocks, &movable, &shift_up(&movable));
+            }
+        }
+    }
+
+    #[cfg(test)]
+    pub fn north_load(&self) -> usize {
+        self.load(Direction::North)
+    }
+
+    /// Each rock's load is its distance from the opposite edge, counting the
+    /// row (or column) it's in.
+    pub fn load(&self, edge: Direction) -> usize {
+        let nrows = self.nrows();
+        match edge {
+            Direction::North | Direction::South => (self.rocks.chunks(self.width).enumerate())
+                .map(|(i, rocks)| {
+                    let load = if edge == Direction::North {
+                        nrows - i
+                    } else {
+                        i + 1
+                    };
+                    let num_rocks: u32 = rocks.iter().map(|r| r.count_ones()).sum();
+                    num_rocks as usize * load
+                })
+                .sum(),
+            Direction::West | Direction::East => (0..self.ncols)
+                .map(|j| {
+                    let num_rocks = (self.rocks.chunks(self.width))
+                        .filter(|rocks| bit(rocks, j))
+                        .count();
+                    let load = if edge == Direction::West {
+                        self.ncols - j
+                    } else {
+                        j + 1
+                    };
+                    num_rocks * load
+                })
+                .sum(),
+        }
+    }
+
+    fn nrows(&self) -> usize {
+        self.rocks.len() / self.width
+    }
+
+    /// Move every rock in row `from` that has space in row `to`. Returns
+    /// whether any moved.
+    fn step_between(&mut self, from: usize, to: usize) -> bool {
+        let mut moved = false;
+        for k in 0..self.width {
+            let (from, to) = (from * self.width + k, to * self.width + k);
+            let movable = self.rocks[from] & !(self.rocks[to] | self.obstacles[to]);
+            self.rocks[from] &= !movable;
+            self.rocks[to] |= movable;
+            moved |= movable != 0;
+        }
+        moved
+    }
+
+    /// The bits of each word that are actually on the board.
+    fn full_masks(&self) -> Vec<u128> {
+        (0..self.width)
+            .map(|k| match self.ncols - 128 * k {
+                n if n >= 128 => u128::MAX,
+                n => (1 << n) - 1,
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Bitboard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in self.to_input().grid {
+            for tile in row {
+                let c = match tile {
+                    Tile::Empty => '.',
+                    Tile::Rock => 'O',
+                    Tile::Obstacle => '#',
+                };
+                write!(f, "{c}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn pack(row: &[Tile], tile: Tile, width: usize) -> Vec<u128> {
+    let mut words = vec![0; width];
+    for (j, _) in row.iter().enumerate().filter(|&(_, &t)| t == tile) {
+        words[j / 128] |= 1 << (j % 128);
+    }
+    words
+}
+
+fn bit(words: &[u128], j: usize) -> bool {
+    words[j / 128] >> (j % 128) & 1 != 0
+}
+
+fn empty_cells(rocks: &[u128], obstacles: &[u128], full: &[u128]) -> Vec<u128> {
+    (rocks.iter().zip(obstacles).zip(full))
+        .map(|((&r, &o), &f)| !(r | o) & f)
+        .collect()
+}
+
+fn and(a: &[u128], b: &[u128]) -> Vec<u128> {
+    a.iter().zip(b).map(|(&x, &y)| x & y).collect()
+}
+
+/// Clear the `from` bits, and set the `to` bits.
+fn move_rocks(rocks: &mut [u128], from: &[u128], to: &[u128]) {
+    for ((r, &f), &t) in rocks.iter_mut().zip(from).zip(to) {
+        *r = (*r & !f) | t;
+    }
+}
+
+/// Every bit moves one column east, carrying between words.
+fn shift_up(words: &[u128]) -> Vec<u128> {
+    (0..words.len())
+        .map(|k| {
+            let carry = if k == 0 { 0 } else { words[k - 1] >> 127 };
+            words[k] << 1 | carry
+        })
+        .collect()
+}
+
+/// Every bit moves one column west, carrying between words.
+fn shift_down(words: &[u128]) -> Vec<u128> {
+    (0..words.len())
+        .map(|k| {
+            let carry = words.get(k + 1).map_or(0, |w| w << 127);
+            words[k] >> 1 | carry
+        })
+        .collect()
+}
@@ -0,0 +1,210 @@
+use std::{collections::HashMap, fmt, iter::Peekable, str::CharIndices};
+
+use anyhow::{bail, ensure, Context, Result};
+
+use super::bitboard::Bitboard;
+
+/// A sequence of tilts, e.g. `N` (part 1) or `(N W S E)^1000000000` (part 2).
+///
+/// Grammar:
+///
+/// ```text
+/// program := step*
+/// step    := atom ('^' COUNT)?
+/// atom    := 'N' | 'E' | 'S' | 'W' | 'P' | '(' program ')'
+/// ```
+///
+/// `P` prints the board. Whitespace is ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    Tilt(Direction),
+    Print,
+    Repeat(Vec<Step>, usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Program {
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut chars = s.char_indices().peekable();
+        let steps = parse_steps(&mut chars)?;
+        if let Some((i, c)) = chars.next() {
+            bail!("column {}: unexpected {c:?}", i + 1);
+        }
+        Ok(Self { steps })
+    }
+
+    /// If `skip_cycles` is set, repeated blocks stop as soon as the board
+    /// returns to an earlier state, and skip ahead. So a `P` inside one only
+    /// prints for the iterations that actually run.
+    pub fn run(&self, board: &mut Bitboard, skip_cycles: bool) {
+        run_steps(&self.steps, board, skip_cycles);
+    }
+}
+
+fn run_steps(steps: &[Step], board: &mut Bitboard, skip_cycles: bool) {
+    for step in steps {
+        match step {
+            Step::Tilt(dir) => board.tilt(*dir),
+            Step::Print => println!("{board}"),
+            Step::Repeat(body, count) => {
+                if skip_cycles {
+                    repeat_with_skipping(body, *count, board);
+                } else {
+                    for _ in 0..*count {
+                        run_steps(body, board, false);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn repeat_with_skipping(body: &[Step], count: usize, board: &mut Bitboard) {
+    let mut seen = HashMap::new();
+    let mut i = 0;
+    while i < count {
+        if let Some(&stem_len) = seen.get(board.state()) {
+            let cycle_len = i - stem_len;
+            for _ in 0..(count - i) % cycle_len {
+                run_steps(body, board, true);
+            }
+            return;
+        }
+        seen.insert(board.state().to_vec(), i);
+
+        run_steps(body, board, true);
+        i += 1;
+    }
+}
+
+type Chars<'a> = Peekable<CharIndices<'a>>;
+
+/// Stops at the end of the input, or at a `)`.
+fn parse_steps(chars: &mut Chars) -> Result<Vec<Step>> {
+    let mut steps = vec![];
+    loop {
+        skip_whitespace(chars);
+        let Some(&(i, c)) = chars.peek() else {
+            return Ok(steps);
+        };
+        let at = || format!("column {}", i + 1);
+
+        let atom = match c.to_ascii_uppercase() {
+            ')' => return Ok(steps),
+            '(' => {
+                chars.next();
+                let body = parse_steps(chars)?;
+                ensure!(
+                    chars.next().map(|(_, c)| c) == Some(')'),
+                    "{}: unclosed '('",
+                    at()
+                );
+                Step::Repeat(body, 1)
+            }
+            'P' => {
+                chars.next();
+                Step::Print
+            }
+            _ => {
+                chars.next();
+                let dir = Direction::new(c).with_context(at)?;
+                Step::Tilt(dir)
+            }
+        };
+
+        skip_whitespace(chars);
+        let step = if chars.peek().map(|&(_, c)| c) == Some('^') {
+            chars.next();
+            skip_whitespace(chars);
+            let count = parse_count(chars)?;
+            match atom {
+                Step::Repeat(body, 1) => Step::Repeat(body, count),
+                other => Step::Repeat(vec![other], count),
+            }
+        } else {
+            atom
+        };
+        steps.push(step);
+    }
+}
+
+fn parse_count(chars: &mut Chars) -> Result<usize> {
+    let Some(&(start, _)) = chars.peek() else {
+        bail!("expected a repeat count at the end of the program");
+    };
+    let mut digits = String::new();
+    while let Some(&(_, c)) = chars.peek() {
+        if !c.is_ascii_digit() {
+            break;
+        }
+        digits.push(c);
+        chars.next();
+    }
+    digits
+        .parse()
+        .with_context(|| format!("column {}: expected a repeat count", start + 1))
+}
+
+fn skip_whitespace(chars: &mut Chars) {
+    while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+}
+
+impl Direction {
+    pub fn new(c: char) -> Result<Self> {
+        Ok(match c.to_ascii_uppercase() {
+            'N' => Self::North,
+            'E' => Self::East,
+            'S' => Self::South,
+            'W' => Self::West,
+            _ => bail!("not a direction: {c:?}"),
+        })
+    }
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_steps(&self.steps, f)
+    }
+}
+
+fn fmt_steps(steps: &[Step], f: &mut fmt::Formatter) -> fmt::Result {
+    for (i, step) in steps.iter().enumerate() {
+        if i != 0 {
+            write!(f, " ")?;
+        }
+        match step {
+            Step::Tilt(dir) => write!(f, "{dir}")?,
+            Step::Print => write!(f, "P")?,
+            Step::Repeat(body, count) => {
+                write!(f, "(")?;
+                fmt_steps(body, f)?;
+                write!(f, ")^{count}")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let c = match self {
+            Self::North => 'N',
+            Self::East => 'E',
+            Self::South => 'S',
+            Self::West => 'W',
+        };
+        write!(f, "{c}")
+    }
+}
@@ -0,0 +1,219 @@
+use anyhow::{ensure, Result};
+use itertools::Itertools;
+use rand::prelude::*;
+
+use super::{
+    bitboard::Bitboard,
+    grid::{self, Input, Tile},
+    program::{Direction, Program},
+    PART_2,
+};
+
+const SAMPLE: &str = "\
+O....#....
+O.OO#....#
+.....##...
+OO.#O....O
+.O.....O#.
+O.#..O.#.#
+..O..#O..O
+.......O..
+#....###..
+#OO..#....
+";
+
+#[test]
+fn sample() -> Result<()> {
+    let input = grid::parse(SAMPLE)?;
+    let mut board = Bitboard::new(&input)?;
+    board.roll_north();
+    ensure!(board.north_load() == 136);
+
+    let mut board = Bitboard::new(&input)?;
+    board.simulate_spin_cycles(10_usize.pow(9));
+    ensure!(board.north_load() == 64);
+    Ok(())
+}
+
+/// Check the bitboard against the original grid implementation.
+#[test]
+fn agrees_with_grid() -> Result<()> {
+    let mut rng = StdRng::seed_from_u64(0);
+
+    let input = grid::parse(SAMPLE)?;
+    check_random_tilts(&input, 1000, &mut rng)?;
+
+    let mut expected = input.clone();
+    expected.simulate_1b_spin_cycles();
+    let mut actual = Bitboard::new(&input)?;
+    actual.simulate_spin_cycles(10_usize.pow(9));
+    ensure!(actual.to_input() == expected, "1b spin cycles");
+    let mut actual = Bitboard::new(&input)?;
+    Program::parse(PART_2)?.run(&mut actual, true);
+    ensure!(actual.to_input() == expected, "part 2 program");
+
+    for _ in 0..1000 {
+        let input = random_input(&mut rng);
+        check_random_tilts(&input, 20, &mut rng)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn skipping_cycles() -> Result<()> {
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..1000 {
+        let input = random_input(&mut rng);
+        check_random_program(&input, &mut rng)?;
+    }
+    Ok(())
+}
+
+/// Rocks cross the 128-column word boundaries in both directions.
+#[test]
+fn wide_board() -> Result<()> {
+    let row = |cells: &[(usize, char)]| {
+        let mut row = vec!['.'; 300];
+        for &(j, c) in cells {
+            row[j] = c;
+        }
+        row.into_iter().collect::<String>()
+    };
+    let mut board = Bitboard::new(&grid::parse(&row(&[(130, '#'), (250, 'O'), (20, 'O')]))?)?;
+    board.roll_west();
+    ensure!(board.to_string() == row(&[(130, '#'), (131, 'O'), (0, 'O')]) + "\n");
+    ensure!(board.load(Direction::West) == 300 + 169);
+    board.roll_east();
+    ensure!(board.to_string() == row(&[(130, '#'), (299, 'O'), (129, 'O')]) + "\n");
+    Ok(())
+}
+
+#[test]
+fn parse_errors() {
+    for (program, expected) in [
+        ("N X", "column 3"),
+        ("(N W", "column 1: unclosed '('"),
+        ("N)", "column 2: unexpected ')'"),
+        ("N^", "expected a repeat count at the end of the program"),
+        ("N^x", "column 3: expected a repeat count"),
+    ] {
+        let err = Program::parse(program).unwrap_err();
+        assert!(
+            format!("{err:#}").starts_with(expected),
+            "{program:?}: {err:#}"
+        );
+    }
+    assert_eq!(
+        Program::parse(" n ( e s )^3 ").unwrap().to_string(),
+        "N (E S)^3"
+    );
+}
+
+/// Tilt both boards the same way, and compare them after every step.
+fn check_random_tilts(input: &Input, num_tilts: usize, rng: &mut impl Rng) -> Result<()> {
+    let mut expected = input.clone();
+    let mut actual = Bitboard::new(input)?;
+    let board = actual.to_string();
+    ensure!(actual.to_input() == expected, "round trip; board:\n{board}");
+
+    let mut tilts = vec![];
+    for _ in 0..num_tilts {
+        let dir = rng.gen_range(0..5);
+        tilts.push(dir);
+        match dir {
+            0 => (expected.roll_north(), actual.roll_north()),
+            1 => (expected.roll_west(), actual.roll_west()),
+            2 => (expected.roll_south(), actual.roll_south()),
+            3 => (expected.roll_east(), actual.roll_east()),
+            _ => (expected.spin_cycle(), actual.spin_cycle()),
+        };
+        ensure!(
+            actual.to_input() == expected,
+            "mismatch after tilts {tilts:?}; board:\n{board}"
+        );
+        ensure!(actual.north_load() == expected.north_load(), "north load");
+        check_loads(&actual)?;
+    }
+    Ok(())
+}
+
+/// Skipping cycles shouldn't change the outcome.
+fn check_random_program(input: &Input, rng: &mut impl Rng) -> Result<()> {
+    let program = Program::parse(&random_program(rng, 2))?;
+    let mut expected = Bitboard::new(input)?;
+    program.run(&mut expected, false);
+    let mut actual = Bitboard::new(input)?;
+    program.run(&mut actual, true);
+    ensure!(actual == expected, "mismatch running {program}");
+    Ok(())
+}
+
+/// Compare against the load computed the slow way, tile by tile.
+fn check_loads(board: &Bitboard) -> Result<()> {
+    let grid = board.to_input().grid;
+    let (nrows, ncols) = (grid.len(), grid[0].len());
+    for edge in [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ] {
+        let mut expected = 0;
+        for (i, row) in grid.iter().enumerate() {
+            for (j, &tile) in row.iter().enumerate() {
+                if tile == Tile::Rock {
+                    expected += match edge {
+                        Direction::North => nrows - i,
+                        Direction::South => i + 1,
+                        Direction::West => ncols - j,
+                        Direction::East => j + 1,
+                    };
+                }
+            }
+        }
+        ensure!(board.load(edge) == expected, "load against {edge}");
+    }
+    Ok(())
+}
+
+/// Small repeat counts, so that it's cheap to run without skipping.
+fn random_program(rng: &mut impl Rng, depth: usize) -> String {
+    let len = rng.gen_range(1..=4);
+    let steps = (0..len).map(|_| {
+        if depth != 0 && rng.gen_bool(0.3) {
+            let body = random_program(rng, depth - 1);
+            format!("({body})^{}", rng.gen_range(0..30))
+        } else {
+            ["N", "E", "S", "W"].choose(rng).unwrap().to_string()
+        }
+    });
+    steps.collect_vec().join(" ")
+}
+
+/// Some boards need more than one word per row.
+fn random_input(rng: &mut impl Rng) -> Input {
+    let nrows = rng.gen_range(1..=20);
+    let ncols = *[1, 2, 7, 64, 65, 127, 128, 129, 255, 256, 300]
+        .choose(rng)
+        .unwrap();
+    let p_rock = rng.gen_range(0.0..0.5);
+    let p_obstacle = rng.gen_range(0.0..0.3);
+
+    let grid = (0..nrows)
+        .map(|_| {
+            (0..ncols)
+                .map(|_| {
+                    let x: f64 = rng.gen();
+                    if x < p_rock {
+                        Tile::Rock
+                    } else if x < p_rock + p_obstacle {
+                        Tile::Obstacle
+                    } else {
+                        Tile::Empty
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    Input { grid }
+}
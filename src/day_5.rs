@@ -1,9 +1,13 @@
 mod input;
 mod part_1;
 mod part_2;
+#[cfg(test)]
+mod tests;
 
 use anyhow::Result;
 
+use self::part_2::Subset;
+
 pub fn solve() -> Result<()> {
     let input = input::read()?;
     let ans = part_1::solve(&input)?;
@@ -12,3 +16,51 @@ pub fn solve() -> Result<()> {
     dbg!(ans);
     Ok(())
 }
+
+/// Print the whole seed-to-location pipeline, as a single map.
+pub fn composed() -> Result<()> {
+    let input = input::read()?;
+    let map = part_2::composed_map(&input)?;
+    println!("seed-to-location map:\n{map}");
+    Ok(())
+}
+
+/// Map a single value from one category to another, e.g. `soil` to `humidity`.
+pub fn query(from: &str, to: &str, x: u64) -> Result<()> {
+    let input = input::read()?;
+    let y = if (from, to) == ("seed", "location") {
+        // The whole pipeline: a binary search in the composed map.
+        let y = part_2::composed_map(&input)?.eval(i128::from(x));
+        u64::try_from(y)?
+    } else {
+        input.categories.eval(from, to, x)?
+    };
+    println!("{from} {x} -> {to} {y}");
+    Ok(())
+}
+
+/// Print every value of `category` (e.g. `seed`) that ends up at a location
+/// in `first..=last`, as ranges; and those that are among the initial seeds.
+pub fn preimage(category: &str, first: u64, last: u64) -> Result<()> {
+    let input = input::read()?;
+    let locations = Subset::range(first, last)?;
+    let values = part_2::preimage(&input, category, &locations)?;
+    println!("{category} ranges:\n{values}");
+
+    if category == "seed" {
+        let mut seeds = values.intersection(&part_2::initial_subset(&input)?);
+        seeds.normalize();
+        println!("initial seed ranges:\n{seeds}");
+    }
+    Ok(())
+}
+
+/// Print the inverse of every stage map, from location back to seed.
+pub fn inverses() -> Result<()> {
+    let input = input::read()?;
+    let inverses = part_2::stage_inverses(&input)?;
+    for (m, inverse) in input.maps.iter().zip(&inverses).rev() {
+        println!("{}-to-{} map:\n{inverse}", m.destination, m.source);
+    }
+    Ok(())
+}
@@ -1,9 +1,10 @@
-use anyhow::{ensure, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
 use itertools::Itertools;
 use once_cell::sync::Lazy;
 use regex::Regex;
 use std::{
     cmp::{max, min},
+    collections::{HashMap, HashSet},
     io,
     result::Result as StdResult,
 };
@@ -11,14 +12,24 @@ use std::{
 #[derive(Debug)]
 pub struct Input {
     pub initial_seeds: Vec<u64>,
+    /// The maps from seed to location, in order.
     pub maps: Vec<Map>,
+    pub categories: CategoryGraph,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Map {
+    pub source: String,
+    pub destination: String,
     pub range_maps: Vec<RangeMap>,
 }
 
+/// Every map in the almanac, keyed by source category.
+#[derive(Debug)]
+pub struct CategoryGraph {
+    maps: HashMap<String, Map>,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct RangeMap {
     pub dest: u64,
@@ -26,10 +37,13 @@ pub struct RangeMap {
     pub len: u64,
 }
 
-/// We rely on the mappings appearing in-order in the input,
-/// and don't validate this.
+/// The maps may appear in any order in the input.
 pub fn read() -> Result<Input> {
     let lines = io::read_to_string(io::stdin())?;
+    parse(&lines)
+}
+
+pub fn parse(lines: &str) -> Result<Input> {
     let mut sections = lines.split("\n\n");
 
     let header = sections.next().context("header")?;
@@ -40,10 +54,15 @@ pub fn read() -> Result<Input> {
         .collect::<StdResult<_, _>>()?;
 
     let maps = sections.map(parse_map).collect::<Result<_>>()?;
+    let categories = CategoryGraph::new(maps)?;
+    let path = categories.path("seed", "location")?;
+    categories.check_chain(&path, "seed", "location")?;
+    let maps = path.into_iter().cloned().collect();
 
     Ok(Input {
         initial_seeds,
         maps,
+        categories,
     })
 }
 
@@ -51,11 +70,17 @@ fn parse_map(section: &str) -> Result<Map> {
     let mut lines = section.lines();
 
     let header = lines.next().context("map header")?;
-    static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\w+-to-\w+ map:$").unwrap());
-    ensure!(RE.is_match(header), "map header regex");
+    static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\w+)-to-(\w+) map:$").unwrap());
+    let caps = RE.captures(header).context("map header regex")?;
+    let source = caps[1].to_string();
+    let destination = caps[2].to_string();
 
     let range_maps = lines.map(parse_range_map).collect::<Result<_>>()?;
-    let map = Map { range_maps };
+    let map = Map {
+        source,
+        destination,
+        range_maps,
+    };
     ensure!(!map.has_overlap(), "overlap");
     Ok(map)
 }
@@ -66,11 +91,82 @@ fn parse_range_map(line: &str) -> Result<RangeMap> {
         .map(str::parse)
         .collect_tuple()
         .context("dest src len")?;
-    Ok(RangeMap {
+    let r = RangeMap {
         dest: dest?,
         src: src?,
         len: len?,
-    })
+    };
+
+    let limit = u128::from(u64::MAX) + 1;
+    ensure!(
+        r.end() <= limit && u128::from(r.dest) + u128::from(r.len) <= limit,
+        "range map {line:?} exceeds u64"
+    );
+    Ok(r)
+}
+
+impl CategoryGraph {
+    fn new(maps: Vec<Map>) -> Result<Self> {
+        let mut by_source: HashMap<String, Map> = HashMap::new();
+        let mut by_destination: HashMap<String, String> = HashMap::new();
+        for m in maps {
+            if let Some(prev) = by_source.get(&m.source) {
+                bail!(
+                    "duplicate stage: maps from {:?} to both {:?} and {:?}",
+                    m.source,
+                    prev.destination,
+                    m.destination,
+                );
+            }
+            if let Some(prev) = by_destination.get(&m.destination) {
+                bail!(
+                    "duplicate stage: maps to {:?} from both {:?} and {:?}",
+                    m.destination,
+                    prev,
+                    m.source,
+                );
+            }
+            by_destination.insert(m.destination.clone(), m.source.clone());
+            by_source.insert(m.source.clone(), m);
+        }
+        Ok(Self { maps: by_source })
+    }
+
+    /// The maps to apply, in order, to get from one category to another.
+    ///
+    /// Maps only run forwards, so this fails if `to` comes before `from`, or
+    /// if either isn't a category at all.
+    pub fn path(&self, from: &str, to: &str) -> Result<Vec<&Map>> {
+        let mut path = vec![];
+        let mut seen = HashSet::new();
+        let mut category = from;
+        while category != to {
+            ensure!(
+                seen.insert(category),
+                "no path from {from:?} to {to:?}: the maps loop back to {category:?}"
+            );
+            let Some(m) = self.maps.get(category) else {
+                bail!("no path from {from:?} to {to:?}: nothing maps from {category:?}");
+            };
+            path.push(m);
+            category = &m.destination;
+        }
+        Ok(path)
+    }
+
+    /// Fails if any map isn't on the path from `from` to `to`.
+    fn check_chain(&self, path: &[&Map], from: &str, to: &str) -> Result<()> {
+        let off_chain = (self.maps.values())
+            .filter(|m| !path.iter().any(|p| p.source == m.source))
+            .map(|m| format!("{}-to-{}", m.source, m.destination))
+            .sorted()
+            .join(", ");
+        ensure!(
+            off_chain.is_empty(),
+            "maps not on the {from}-to-{to} chain: {off_chain}"
+        );
+        Ok(())
+    }
 }
 
 impl Map {
@@ -93,17 +189,18 @@ impl RangeMap {
     }
 
     fn intersection(&self, other: &Self) -> Interval {
-        let start = max(self.src, other.src);
+        let start = max(self.src, other.src).into();
         let end = min(self.end(), other.end());
         (start, end)
     }
 
-    fn end(&self) -> u64 {
-        self.src + self.len
+    /// One past the last source value. This may not fit in a u64.
+    pub fn end(&self) -> u128 {
+        u128::from(self.src) + u128::from(self.len)
     }
 }
 
-type Interval = (u64, u64);
+type Interval = (u128, u128);
 
 fn is_empty((start, end): Interval) -> bool {
     start >= end
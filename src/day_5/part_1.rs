@@ -1,6 +1,6 @@
 use anyhow::{ensure, Context, Result};
 
-use super::input::{Input, Map, RangeMap};
+use super::input::{CategoryGraph, Input, Map, RangeMap};
 
 pub fn solve(input: &Input) -> Result<u64> {
     input
@@ -16,25 +16,48 @@ pub fn solve(input: &Input) -> Result<u64> {
 /// This takes ~3 mins to run on my laptop.
 #[allow(dead_code)]
 pub fn solve_part_2(input: &Input) -> Result<u64> {
-    ensure!(input.initial_seeds.len() % 2 == 0, "odd number of seeds");
-    let initial_seeds = input.initial_seeds.chunks(2).flat_map(|pair| {
+    ensure!(
+        input.initial_seeds.len().is_multiple_of(2),
+        "odd number of seeds"
+    );
+    let mut ranges = vec![];
+    for pair in input.initial_seeds.chunks(2) {
         let &[start, len] = pair else { unreachable!() };
-        start..start + len
-    });
+        // An empty range contributes no seeds.
+        if len == 0 {
+            continue;
+        }
+        let last = start
+            .checked_add(len - 1)
+            .context("seed range exceeds u64")?;
+        ranges.push(start..=last);
+    }
 
-    initial_seeds
+    ranges
+        .into_iter()
+        .flatten()
         .map(|x| eval_all(&input.maps, x))
         .min()
         .context("no initial seeds")
 }
 
-fn eval_all(maps: &[Map], mut x: u64) -> u64 {
+pub fn eval_all(maps: &[Map], mut x: u64) -> u64 {
     for m in maps {
         x = m.eval(x);
     }
     x
 }
 
+impl CategoryGraph {
+    /// Map a value from one category to another.
+    pub fn eval(&self, from: &str, to: &str, mut x: u64) -> Result<u64> {
+        for m in self.path(from, to)? {
+            x = m.eval(x);
+        }
+        Ok(x)
+    }
+}
+
 impl Map {
     fn eval(&self, x: u64) -> u64 {
         for r in &self.range_maps {
@@ -48,13 +71,13 @@ impl Map {
 
 impl RangeMap {
     fn contains(self, x: u64) -> bool {
-        self.src <= x && x < self.src + self.len
+        self.src <= x && x - self.src < self.len
     }
 
+    /// The input is validated so that this can't overflow.
     fn map(self, x: u64) -> u64 {
         assert!(self.contains(x));
         let offset = x - self.src;
-        let y = self.dest + offset;
-        y
+        self.dest + offset
     }
 }
@@ -1,21 +1,74 @@
+mod compose;
 mod input;
+mod inverse;
 
 use std::cmp::{max, min};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, ensure, Context, Result};
 
+use self::inverse::Argmin;
 use super::input::Input as RawInput;
 
-pub fn solve(input: &RawInput) -> Result<i64> {
+pub fn solve(input: &RawInput) -> Result<Argmin> {
+    let map = composed_map(input)?;
     let input = Input::from_raw(input)?;
+    let ans = map.argmin(&input.initial_subset).context("empty subset")?;
+
+    // Double-check the answer by running the pipeline backwards.
+    let seeds = map
+        .preimage(&Subset::point(ans.location))
+        .intersection(&input.initial_subset);
+    ensure!(
+        seeds.contains(ans.seed),
+        "seed {} not in preimage",
+        ans.seed
+    );
+
+    Ok(ans)
+}
+
+/// The whole seed-to-location pipeline, as a single map.
+pub fn composed_map(input: &RawInput) -> Result<Map> {
+    Ok(Input::from_raw(input)?.composed_map())
+}
 
-    let mut subset = input.initial_subset.clone();
-    for map in &input.maps {
-        subset = map.subset_image(&subset);
+/// The initial seeds, read as ranges.
+pub fn initial_subset(input: &RawInput) -> Result<Subset> {
+    Ok(Input::from_raw(input)?.initial_subset)
+}
+
+/// The inverse of every stage map, from seed-to-soil to humidity-to-location.
+pub fn stage_inverses(input: &RawInput) -> Result<Vec<Map>> {
+    Ok(Input::from_raw(input)?
+        .maps
+        .iter()
+        .map(Map::inverse)
+        .collect())
+}
+
+/// All values of `category` that end up at a location in `locations`, found by
+/// running the stage maps backwards.
+pub fn preimage(input: &RawInput, category: &str, locations: &Subset) -> Result<Subset> {
+    let start = match input.maps.iter().position(|m| m.source == category) {
+        Some(i) => i,
+        None if category == "location" => input.maps.len(),
+        None => bail!("not a category: {category:?}"),
+    };
+
+    let mut out = locations.clone();
+    for inverse in stage_inverses(input)?[start..].iter().rev() {
+        out = inverse.subset_image(&out);
     }
-    subset.min_value().context("empty subset")
+    out.normalize();
+    Ok(out)
 }
 
+/// One past the largest u64.
+///
+/// Almanac values are u64s, so we work in i128 to represent the end of
+/// intervals (and the offsets of range maps) without overflow.
+const DOMAIN_END: i128 = 1 << 64;
+
 #[derive(Debug)]
 struct Input {
     initial_subset: Subset,
@@ -23,7 +76,7 @@ struct Input {
 }
 
 #[derive(Debug, Clone)]
-struct Subset {
+pub struct Subset {
     /// Ideally these would always be non-overlapping, but we're currently not
     /// enforcing that.
     ranges: Vec<Interval>,
@@ -32,20 +85,20 @@ struct Subset {
 /// Left-inclusive; possibly empty.
 #[derive(Debug, Clone, Copy)]
 struct Interval {
-    start: i64,
-    end: i64,
+    start: i128,
+    end: i128,
 }
 
 #[derive(Debug)]
-struct Map {
-    /// Invariant: these must *cover* the non-negative number line.
+pub struct Map {
+    /// Invariant: these must *cover* the domain, `0..DOMAIN_END`.
     range_maps: Vec<RangeMap>,
 }
 
 #[derive(Debug, Clone, Copy)]
 struct RangeMap {
     input: Interval,
-    output_start: i64,
+    output_start: i128,
 }
 
 impl Map {
@@ -82,7 +135,7 @@ impl Interval {
         self.start >= self.end
     }
 
-    fn translate(self, offset: i64) -> Self {
+    fn translate(self, offset: i128) -> Self {
         Self {
             start: self.start + offset,
             end: self.end + offset,
@@ -91,13 +144,7 @@ impl Interval {
 }
 
 impl RangeMap {
-    fn offset(self) -> i64 {
+    fn offset(self) -> i128 {
         self.output_start - self.input.start
     }
 }
-
-impl Subset {
-    fn min_value(&self) -> Option<i64> {
-        self.ranges.iter().map(|r| r.start).min()
-    }
-}
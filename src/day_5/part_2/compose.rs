@@ -0,0 +1,124 @@
+use std::fmt;
+
+#[cfg(test)]
+use anyhow::{ensure, Result};
+
+use crate::day_5::part_2::{Input, Interval, Map, RangeMap, DOMAIN_END};
+#[cfg(test)]
+use crate::day_5::{input::Input as RawInput, part_1::eval_all};
+
+impl Input {
+    /// Compose all the maps into a single seed-to-location map.
+    pub fn composed_map(&self) -> Map {
+        let mut out = Map::identity();
+        for m in &self.maps {
+            out = out.then(m);
+        }
+        out
+    }
+}
+
+impl Map {
+    fn identity() -> Self {
+        let everything = Interval {
+            start: 0,
+            end: DOMAIN_END,
+        };
+        Self {
+            range_maps: vec![RangeMap {
+                input: everything,
+                output_start: 0,
+            }],
+        }
+    }
+
+    /// The map `x -> other(self(x))`.
+    ///
+    /// The result is normalized: its range maps are sorted and
+    /// non-overlapping, and no two adjacent range maps have the same offset.
+    pub fn then(&self, other: &Self) -> Self {
+        let mut range_maps = vec![];
+        for &r in &self.range_maps {
+            let image = r.input.translate(r.offset());
+            for &s in &other.range_maps {
+                let segment = Interval::intersection(image, s.input);
+                if !segment.is_empty() {
+                    let input = segment.translate(-r.offset());
+                    range_maps.push(RangeMap {
+                        input,
+                        output_start: input.start + r.offset() + s.offset(),
+                    });
+                }
+            }
+        }
+
+        let mut out = Self { range_maps };
+        out.normalize();
+        out
+    }
+
+    /// Sort the range maps, and merge adjacent ones that have the same offset.
+    ///
+    /// Assumes the range maps are non-overlapping.
+    fn normalize(&mut self) {
+        self.range_maps.sort_by_key(|r| r.input.start);
+
+        let mut out: Vec<RangeMap> = vec![];
+        for r in self.range_maps.drain(..) {
+            match out.last_mut() {
+                Some(prev) if prev.input.end == r.input.start && prev.offset() == r.offset() => {
+                    prev.input.end = r.input.end;
+                }
+                _ => out.push(r),
+            }
+        }
+        self.range_maps = out;
+    }
+
+    /// Evaluate the map at a point, in O(log n) time.
+    ///
+    /// Assumes the map is normalized, and that it covers x.
+    pub fn eval(&self, x: i128) -> i128 {
+        let i = self.range_maps.partition_point(|r| r.input.end <= x);
+        let r = self.range_maps[i];
+        assert!(r.input.start <= x && x < r.input.end);
+        x + r.offset()
+    }
+
+    /// Check that this map agrees with evaluating the raw maps one-by-one.
+    ///
+    /// We try the initial seeds, as well as the edges of every raw range map.
+    #[cfg(test)]
+    pub fn check_against(&self, raw: &RawInput) -> Result<()> {
+        let edges = raw.maps.iter().flat_map(|m| &m.range_maps).flat_map(|r| {
+            let end = r.end();
+            [
+                r.src.checked_sub(1),
+                Some(r.src),
+                u64::try_from(end - 1).ok(),
+                u64::try_from(end).ok(),
+            ]
+        });
+
+        for x in raw.initial_seeds.iter().copied().chain(edges.flatten()) {
+            let expected = eval_all(&raw.maps, x);
+            let actual = self.eval(i128::from(x));
+            ensure!(
+                actual == i128::from(expected),
+                "composed map disagrees at {x}: {actual} != {expected}"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Written in the same `dest src len` format as the input, one range per line.
+impl fmt::Display for Map {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for r in &self.range_maps {
+            let len = r.input.end - r.input.start;
+            writeln!(f, "{} {} {len}", r.output_start, r.input.start)?;
+        }
+        Ok(())
+    }
+}
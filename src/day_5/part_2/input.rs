@@ -3,18 +3,26 @@ use itertools::Itertools;
 
 use crate::day_5::{
     input::{self as raw_input, Input as RawInput},
-    part_2::{Input, Interval, Map, RangeMap, Subset},
+    part_2::{Input, Interval, Map, RangeMap, Subset, DOMAIN_END},
 };
 
 impl Input {
     /// Massage types, to get the input in a pleasant shape for solving part 2.
     pub fn from_raw(input: &RawInput) -> Result<Input> {
-        ensure!(input.initial_seeds.len() % 2 == 0, "odd number of seeds");
+        ensure!(
+            input.initial_seeds.len().is_multiple_of(2),
+            "odd number of seeds"
+        );
 
         let mut ranges = vec![];
         for pair in input.initial_seeds.chunks(2) {
             let &[start, len] = pair else { unreachable!() };
-            ranges.push(Interval::from_start_len(start, len));
+            let r = Interval::from_start_len(start, len);
+            ensure!(r.end <= DOMAIN_END, "seed range {start} {len} exceeds u64");
+            // An empty range contributes no seeds.
+            if !r.is_empty() {
+                ranges.push(r);
+            }
         }
         let initial_subset = Subset { ranges };
 
@@ -29,17 +37,17 @@ impl Input {
 
 impl Interval {
     fn from_start_len(start: u64, len: u64) -> Self {
-        let end = start + len;
+        let start = i128::from(start);
         Self {
-            start: start as i64,
-            end: end as i64,
+            start,
+            end: start + i128::from(len),
         }
     }
 }
 
 impl Map {
     /// Fill out the map with identity RangeMaps, so that it covers the entire
-    /// non-negative number line.
+    /// domain.
     fn from_raw_input(map: &raw_input::Map) -> Self {
         let range_maps = map
             .range_maps
@@ -70,7 +78,7 @@ impl Subset {
             .ranges
             .iter()
             .flat_map(|r| [r.start, r.end])
-            .chain([0, i64::MAX])
+            .chain([0, DOMAIN_END])
             .sorted();
 
         let mut out = vec![];
@@ -89,7 +97,7 @@ impl RangeMap {
     fn from_raw_input(r: &raw_input::RangeMap) -> Self {
         Self {
             input: Interval::from_start_len(r.src, r.len),
-            output_start: r.dest as i64,
+            output_start: i128::from(r.dest),
         }
     }
 
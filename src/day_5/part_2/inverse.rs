@@ -0,0 +1,124 @@
+use std::fmt;
+
+use anyhow::{ensure, Result};
+
+use crate::day_5::part_2::{Interval, Map, RangeMap, Subset, DOMAIN_END};
+
+/// The smallest output of a map over some subset, along with an input that
+/// achieves it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Argmin {
+    pub seed: i128,
+    pub location: i128,
+}
+
+impl Map {
+    /// Swap the inputs and outputs of every range map.
+    ///
+    /// Each range map is injective, so the inverse sends every output back to
+    /// all the inputs that lead to it. If `self` isn't a bijection, the range
+    /// maps of the inverse may overlap; so use `subset_image` on the result
+    /// rather than evaluating it at points.
+    pub fn inverse(&self) -> Self {
+        let mut range_maps: Vec<_> = self
+            .range_maps
+            .iter()
+            .map(|r| RangeMap {
+                input: r.input.translate(r.offset()),
+                output_start: r.input.start,
+            })
+            .collect();
+        range_maps.sort_by_key(|r| r.input.start);
+        Self { range_maps }
+    }
+
+    /// All inputs whose output lies in `outputs`.
+    pub fn preimage(&self, outputs: &Subset) -> Subset {
+        self.inverse().subset_image(outputs)
+    }
+
+    /// The smallest output over `inputs`, and the input that achieves it.
+    pub fn argmin(&self, inputs: &Subset) -> Option<Argmin> {
+        let mut candidates = vec![];
+        for &input in &inputs.ranges {
+            for rmap in &self.range_maps {
+                let segment = Interval::intersection(rmap.input, input);
+                if !segment.is_empty() {
+                    candidates.push(Argmin {
+                        seed: segment.start,
+                        location: segment.start + rmap.offset(),
+                    });
+                }
+            }
+        }
+        candidates.into_iter().min_by_key(|a| (a.location, a.seed))
+    }
+}
+
+impl Subset {
+    /// The values `first..=last`.
+    pub fn range(first: u64, last: u64) -> Result<Self> {
+        ensure!(first <= last, "empty range {first}..={last}");
+        let end = i128::from(last) + 1;
+        assert!(end <= DOMAIN_END);
+        Ok(Self {
+            ranges: vec![Interval {
+                start: i128::from(first),
+                end,
+            }],
+        })
+    }
+
+    pub fn point(x: i128) -> Self {
+        Self {
+            ranges: vec![Interval {
+                start: x,
+                end: x + 1,
+            }],
+        }
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut out = vec![];
+        for &a in &self.ranges {
+            for &b in &other.ranges {
+                let r = Interval::intersection(a, b);
+                if !r.is_empty() {
+                    out.push(r);
+                }
+            }
+        }
+        Self { ranges: out }
+    }
+
+    pub fn contains(&self, x: i128) -> bool {
+        self.ranges.iter().any(|r| r.start <= x && x < r.end)
+    }
+}
+
+impl Subset {
+    /// Sort the ranges, and merge any that overlap or touch.
+    pub fn normalize(&mut self) {
+        self.ranges.retain(|r| !r.is_empty());
+        self.ranges.sort_by_key(|r| r.start);
+
+        let mut out: Vec<Interval> = vec![];
+        for r in self.ranges.drain(..) {
+            match out.last_mut() {
+                Some(prev) if prev.end >= r.start => prev.end = prev.end.max(r.end),
+                _ => out.push(r),
+            }
+        }
+        self.ranges = out;
+    }
+}
+
+/// Written in the same `start len` format as the seed ranges, one per line.
+impl fmt::Display for Subset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for r in &self.ranges {
+            writeln!(f, "{} {}", r.start, r.end - r.start)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+use std::fmt::Write;
+
+use anyhow::{ensure, Context, Result};
+use itertools::Itertools;
+use rand::prelude::*;
+
+use super::{input, part_1, part_2};
+
+const CATEGORIES: [&str; 8] = [
+    "seed",
+    "soil",
+    "fertilizer",
+    "water",
+    "light",
+    "temperature",
+    "humidity",
+    "location",
+];
+
+/// One past the largest u64.
+const TOP: u128 = 1 << 64;
+
+/// All values in a generated almanac lie within this distance of `TOP`.
+const SPREAD: u128 = 2000;
+
+/// Generate a random almanac in the puzzle's input format, with all values
+/// near `u64::MAX`, and with the maps in a random order.
+///
+/// Some range maps (and seed ranges) end exactly at `u64::MAX`.
+fn random_almanac(rng: &mut impl Rng) -> String {
+    let mut out = String::from("seeds:");
+    for _ in 0..rng.gen_range(1..=3) {
+        let start = rng.gen_range(TOP - SPREAD..TOP);
+        let len = rng.gen_range(1..=(TOP - start).min(200));
+        write!(out, " {start} {len}").unwrap();
+    }
+    out.push('\n');
+
+    let mut sections = vec![];
+    for (source, destination) in CATEGORIES.iter().tuple_windows() {
+        let mut section = format!("{source}-to-{destination} map:\n");
+
+        // Cut the top of the range into pieces, and map some of them.
+        let mut cuts: Vec<u128> = (0..rng.gen_range(1..=6))
+            .map(|_| rng.gen_range(TOP - SPREAD..TOP))
+            .chain([TOP])
+            .collect();
+        cuts.sort();
+        cuts.dedup();
+        for (src, end) in cuts.into_iter().tuple_windows() {
+            if rng.gen_bool(0.75) {
+                let len = end - src;
+                let dest = rng.gen_range(TOP - SPREAD..=TOP - len);
+                writeln!(section, "{dest} {src} {len}").unwrap();
+            }
+        }
+        sections.push(section);
+    }
+    sections.shuffle(rng);
+
+    for s in sections {
+        out.push('\n');
+        out.push_str(&s);
+    }
+    out
+}
+
+/// Check that every solver agrees on a random almanac near `u64::MAX`.
+fn check_near_u64_max(rng: &mut impl Rng) -> Result<()> {
+    let text = random_almanac(rng);
+    let input = input::parse(&text).with_context(|| format!("almanac:\n{text}"))?;
+
+    let map = part_2::composed_map(&input)?;
+    map.check_against(&input)
+        .with_context(|| format!("almanac:\n{text}"))?;
+    for &x in &input.initial_seeds {
+        let location = input.categories.eval("seed", "location", x)?;
+        ensure!(
+            map.eval(i128::from(x)) == i128::from(location),
+            "composed map disagrees at {x}; almanac:\n{text}"
+        );
+    }
+
+    for &x in &input.initial_seeds {
+        let location = input.categories.eval("seed", "location", x)?;
+        let locations = part_2::Subset::range(location, location)?;
+        for category in CATEGORIES {
+            let y = input.categories.eval("seed", category, x)?;
+            let values = part_2::preimage(&input, category, &locations)?;
+            ensure!(
+                values.contains(i128::from(y)),
+                "{category} {y} not in the preimage of location {location}; almanac:\n{text}"
+            );
+        }
+    }
+
+    let brute_force = part_1::solve_part_2(&input)?;
+    let ans = part_2::solve(&input)?;
+    ensure!(
+        ans.location == i128::from(brute_force),
+        "part 2: {ans:?} != {brute_force}; almanac:\n{text}"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn near_u64_max() -> Result<()> {
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..1000 {
+        check_near_u64_max(&mut rng)?;
+    }
+    Ok(())
+}
+
+/// Maps that run past `u64::MAX` are a parse error, rather than wrapping.
+#[test]
+fn rejects_overflow() -> Result<()> {
+    let max = u64::MAX;
+    let inputs = [
+        format!("seeds: 0\n\nseed-to-location map:\n0 {max} 2\n"),
+        format!("seeds: 0\n\nseed-to-location map:\n{max} 0 2\n"),
+    ];
+    for text in inputs {
+        ensure!(input::parse(&text).is_err(), "accepted:\n{text}");
+    }
+    Ok(())
+}
+
+/// A zero-length seed range contributes no seeds, in both solvers.
+#[test]
+fn empty_seed_range() -> Result<()> {
+    let text = "seeds: 5 0 10 2\n\nseed-to-location map:\n100 10 1\n";
+    let input = input::parse(text)?;
+    ensure!(part_1::solve_part_2(&input)? == 11);
+    ensure!(part_2::solve(&input)?.location == 11);
+
+    let text = "seeds: 5 0\n\nseed-to-location map:\n100 10 1\n";
+    let input = input::parse(text)?;
+    ensure!(part_1::solve_part_2(&input).is_err());
+    ensure!(part_2::solve(&input).is_err());
+    Ok(())
+}
+
+fn error_message(text: &str) -> String {
+    match input::parse(text) {
+        Ok(_) => panic!("accepted:\n{text}"),
+        Err(e) => e.to_string(),
+    }
+}
+
+#[test]
+fn rejects_bad_chains() {
+    let seeds = "seeds: 1\n\n";
+    let maps = [
+        "seed-to-soil map:\n",
+        "soil-to-location map:\n",
+        "water-to-location map:\n",
+        "seed-to-water map:\n",
+        "light-to-dark map:\n",
+    ];
+    let almanac = |ids: &[usize]| seeds.to_string() + &ids.iter().map(|&i| maps[i]).join("\n");
+
+    assert!(input::parse(&almanac(&[0, 1])).is_ok());
+    assert!(error_message(&almanac(&[0, 1, 2])).contains("maps to \"location\" from both"));
+    assert!(error_message(&almanac(&[0, 1, 3])).contains("maps from \"seed\" to both"));
+    assert_eq!(
+        error_message(&almanac(&[0, 1, 4])),
+        "maps not on the seed-to-location chain: light-to-dark"
+    );
+    assert_eq!(
+        error_message(&almanac(&[0])),
+        "no path from \"seed\" to \"location\": nothing maps from \"soil\""
+    );
+}
+
+#[test]
+fn paths_only_run_forwards() -> Result<()> {
+    let text = "seeds: 1\n\nseed-to-soil map:\n5 1 1\n\nsoil-to-location map:\n";
+    let graph = input::parse(text)?.categories;
+    ensure!(graph.eval("seed", "location", 1)? == 5);
+    ensure!(graph.eval("soil", "soil", 1)? == 1);
+
+    let err = graph.eval("location", "seed", 5).unwrap_err();
+    ensure!(
+        err.to_string() == "no path from \"location\" to \"seed\": nothing maps from \"location\""
+    );
+    Ok(())
+}
+
+#[test]
+fn preimage_of_a_range() -> Result<()> {
+    let text = "seeds: 0 10\n\nseed-to-soil map:\n20 0 5\n\nsoil-to-location map:\n0 20 3\n";
+    let input = input::parse(text)?;
+    let locations = part_2::Subset::range(1, 21)?;
+
+    // Soils 21 and 22 map to locations 1 and 2, and soils 1 to 19 are left
+    // alone. Seeds 1 and 2 map to soils 21 and 22, as do seeds 21 and 22; and
+    // only seeds 5 to 19 are left alone.
+    let seeds = part_2::preimage(&input, "seed", &locations)?;
+    ensure!(seeds.to_string() == "1 2\n5 15\n21 2\n", "seeds:\n{seeds}");
+    let soils = part_2::preimage(&input, "soil", &locations)?;
+    ensure!(soils.to_string() == "1 19\n21 2\n", "soils:\n{soils}");
+    ensure!(part_2::preimage(&input, "water", &locations).is_err());
+    Ok(())
+}
@@ -0,0 +1,40 @@
+mod sequence;
+
+use std::io;
+
+use anyhow::Result;
+use itertools::Itertools;
+use num_bigint::BigInt;
+
+use self::sequence::Newton;
+
+/// Forecast each line `steps` terms ahead (or behind, if negative), and sum
+/// the results. Part 1 is `steps = 1`, and part 2 is `steps = -1`.
+///
+/// If `show_polynomials` is set, also print the closed form of each line.
+pub fn solve(steps: i64, show_polynomials: bool) -> Result<()> {
+    let lines = read_input()?;
+
+    let mut sum = BigInt::ZERO;
+    for l in lines {
+        let poly = Newton::fit(&l)?;
+        if show_polynomials {
+            match poly.degree() {
+                Some(d) => println!("degree {d}: {poly}"),
+                None => println!("all zeros: {poly}"),
+            }
+        }
+        sum += poly.forecast(steps);
+    }
+    dbg!(sum);
+
+    Ok(())
+}
+
+fn read_input() -> Result<Vec<Vec<i64>>> {
+    io::stdin().lines().map(parse_line).collect()
+}
+
+fn parse_line(line: io::Result<String>) -> Result<Vec<i64>> {
+    Ok(line?.split_whitespace().map(str::parse).try_collect()?)
+}
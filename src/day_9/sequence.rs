@@ -0,0 +1,175 @@
+use std::fmt;
+
+use anyhow::{ensure, Result};
+use num_bigint::BigInt;
+
+/// A sequence that's given by a polynomial, in Newton's forward-difference
+/// form:
+///
+/// `f(n) = a_0 + a_1 * C(n, 1) + a_2 * C(n, 2) + ... + a_d * C(n, d)`
+///
+/// where `a_j` is the j-th difference at the start of the sequence, and `C` is
+/// the binomial coefficient. This works for negative n, too.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Newton {
+    coefficients: Vec<BigInt>,
+    /// The length of the original sequence.
+    len: usize,
+}
+
+impl Newton {
+    /// Build the full difference table, and read off the first column.
+    ///
+    /// Fails if the differences never become all zeros; i.e., if the sequence
+    /// isn't given by a polynomial of degree less than `seq.len() - 1`.
+    pub fn fit(seq: &[i64]) -> Result<Self> {
+        ensure!(!seq.is_empty(), "empty sequence");
+
+        let mut row: Vec<BigInt> = seq.iter().copied().map(BigInt::from).collect();
+        let mut coefficients = vec![];
+        while !row.iter().all(|x| *x == BigInt::ZERO) {
+            ensure!(
+                row.len() >= 2,
+                "differences never reach all zeros: {seq:?} isn't a polynomial of degree < {}",
+                seq.len() - 1
+            );
+            coefficients.push(row[0].clone());
+            row = row.windows(2).map(|w| &w[1] - &w[0]).collect();
+        }
+
+        Ok(Self {
+            coefficients,
+            len: seq.len(),
+        })
+    }
+
+    /// The degree of the polynomial. The all-zeros sequence has degree None.
+    pub fn degree(&self) -> Option<usize> {
+        self.coefficients.len().checked_sub(1)
+    }
+
+    /// The n-th term of the sequence; the original sequence is terms
+    /// `0..len`.
+    pub fn eval(&self, n: i64) -> BigInt {
+        self.eval_at(&BigInt::from(n))
+    }
+
+    fn eval_at(&self, n: &BigInt) -> BigInt {
+        let mut total = BigInt::ZERO;
+        // C(n, j), built up incrementally.
+        let mut binomial = BigInt::from(1);
+        for (j, a) in self.coefficients.iter().enumerate() {
+            total += a * &binomial;
+            binomial = binomial * (n - j) / (j + 1);
+        }
+        total
+    }
+
+    /// The term `k` steps after the end of the sequence; or, if `k` is
+    /// negative, `|k|` steps before the start.
+    pub fn forecast(&self, k: i64) -> BigInt {
+        if k >= 0 {
+            // Past the end of i64, for large enough k.
+            self.eval_at(&(BigInt::from(self.len - 1) + k))
+        } else {
+            self.eval(k)
+        }
+    }
+}
+
+impl fmt::Display for Newton {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "f(n) =")?;
+        if self.coefficients.is_empty() {
+            return write!(f, " 0");
+        }
+        for (j, a) in self.coefficients.iter().enumerate() {
+            let sep = if j == 0 { " " } else { " + " };
+            match j {
+                0 => write!(f, "{sep}{a}")?,
+                _ => write!(f, "{sep}{a}*C(n, {j})")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+
+    const SAMPLE: [&[i64]; 3] = [
+        &[0, 3, 6, 9, 12, 15],
+        &[1, 3, 6, 10, 15, 21],
+        &[10, 13, 16, 21, 30, 45],
+    ];
+
+    #[test]
+    fn sample() -> Result<()> {
+        let polys: Vec<_> = SAMPLE.iter().map(|seq| Newton::fit(seq)).try_collect()?;
+        let degrees = polys.iter().map(Newton::degree).collect_vec();
+        assert_eq!(degrees, [Some(1), Some(2), Some(3)]);
+
+        let forward: BigInt = polys.iter().map(|p| p.forecast(1)).sum();
+        assert_eq!(forward, 114.into());
+        let backward: BigInt = polys.iter().map(|p| p.forecast(-1)).sum();
+        assert_eq!(backward, 2.into());
+
+        // The fit reproduces the original sequence.
+        for (seq, poly) in SAMPLE.iter().zip(&polys) {
+            let terms = (0..seq.len() as i64).map(|n| poly.eval(n)).collect_vec();
+            assert_eq!(terms, seq.iter().map(|&x| BigInt::from(x)).collect_vec());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn several_steps() -> Result<()> {
+        let [linear, triangular, cubic] = SAMPLE.map(|seq| Newton::fit(seq).unwrap());
+        assert_eq!(linear.forecast(0), 15.into());
+        assert_eq!(linear.forecast(3), 24.into());
+        assert_eq!(linear.forecast(-3), (-9).into());
+
+        // The triangular numbers, (n + 1)(n + 2)/2.
+        assert_eq!(triangular.forecast(2), 36.into());
+        assert_eq!(triangular.forecast(-2), 0.into());
+        assert_eq!(triangular.forecast(-3), 1.into());
+
+        assert_eq!(cubic.forecast(2), 101.into());
+
+        // Far past the end of i64.
+        let far = (BigInt::from(i64::MAX) + 5) * 3;
+        assert_eq!(linear.forecast(i64::MAX), far);
+        assert_eq!(linear.forecast(i64::MIN), BigInt::from(i64::MIN) * 3);
+        Ok(())
+    }
+
+    #[test]
+    fn constants() -> Result<()> {
+        let zeros = Newton::fit(&[0, 0, 0])?;
+        assert_eq!(zeros.degree(), None);
+        assert_eq!(zeros.forecast(10), 0.into());
+        assert_eq!(zeros.to_string(), "f(n) = 0");
+
+        let sevens = Newton::fit(&[7, 7])?;
+        assert_eq!(sevens.degree(), Some(0));
+        assert_eq!(sevens.forecast(-10), 7.into());
+        assert_eq!(sevens.to_string(), "f(n) = 7");
+        Ok(())
+    }
+
+    /// Too short for the differences to reach all zeros.
+    #[test]
+    fn not_a_polynomial() {
+        for seq in [&[][..], &[5], &[1, 2, 4], &[1, 2, 4, 8, 16]] {
+            assert!(Newton::fit(seq).is_err(), "{seq:?}");
+        }
+        let err = Newton::fit(&[1, 2, 4]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "differences never reach all zeros: [1, 2, 4] isn't a polynomial of degree < 2"
+        );
+    }
+}
@@ -1,2 +1,5 @@
+pub mod day_12;
+pub mod day_14;
 pub mod day_18;
 pub mod day_5;
+pub mod day_9;